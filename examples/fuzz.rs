@@ -0,0 +1,19 @@
+// Plays random games against the rules engine and stops at the first broken
+// invariant, a failure prints the seed that reproduces it. Run with
+// `cargo run --example fuzz --features fuzz -- [games] [seed]`.
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let games = args.next().and_then(|arg| arg.parse().ok()).unwrap_or(1000);
+    let seed = args
+        .next()
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or_else(rand::random);
+
+    match mart::server::fuzz::fuzz_games(games, seed) {
+        Ok(played) => println!("{} games, {} inputs, seed {}: ok", games, played, seed),
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use crate::client::{
-    components::in_game::InGameView,
+    components::{in_game::InGameView, lobby::LobbyView, room::RoomView},
     error_template::{AppError, ErrorTemplate},
 };
 use leptos::*;
@@ -25,7 +25,9 @@ pub fn App() -> impl IntoView {
         }>
             <main>
                 <Routes>
-                    <Route path="" view=InGameView/>
+                    <Route path="" view=LobbyView/>
+                    <Route path="/room/:room_id" view=RoomView/>
+                    <Route path="/game/:game_id" view=InGameView/>
                 </Routes>
             </main>
         </Router>
@@ -2,11 +2,12 @@ use crate::client::components::{
     card::CardView, card_landing::CardLandingView, ident_icon::PlayerIconView,
     money_input::MoneyInputView,
 };
-use crate::client::websocket::Ws;
+use crate::client::websocket::{PendingInputs, Ws};
 use crate::common::game_state::{AuctionState, AuctionTarget, CardPair, Money, MoneyPair};
 use crate::common::placeholder::PlaceHolder;
 use crate::common::{
     card::Card,
+    error::GameError,
     game_state::{GameStage, GameState},
     input::*,
     player::{Player, PlayerID},
@@ -184,6 +185,7 @@ fn WaitingForCardView() -> impl IntoView {
     let ws: Ws = expect_context();
 
     let not_selected = move || selected_card().is_none();
+    let error = use_rule_check(move || selected_card().map(|card| ActionInput::PlayCard(card.id)));
     view! {
         <Panel
             subview=SubView::WaitingForCard
@@ -196,7 +198,7 @@ fn WaitingForCardView() -> impl IntoView {
             <Inactive slot>()</Inactive>
             <Action slot>
                 <button
-                    prop:disabled=not_selected
+                    prop:disabled=move || not_selected() || error().is_some()
                     on:click=move |_| {
                         ws.get_value()
                             .send_game_input(ActionInput::PlayCard(selected_card().unwrap().id))
@@ -205,6 +207,7 @@ fn WaitingForCardView() -> impl IntoView {
 
                     "Confirm"
                 </button>
+                <RuleHintView error/>
             </Action>
         </Panel>
     }
@@ -216,6 +219,10 @@ fn WaitingForDoubleTargetView(double_card: Signal<CardPair>) -> impl IntoView {
     let ws: Ws = expect_context();
 
     let not_selected = move || selected_card().is_none();
+    let error = use_rule_check(move || {
+        selected_card()
+            .map(|card| ActionInput::PlayCardOptional(PlayCardOptionalInner::Play(card.id)))
+    });
     let player: Signal<Player> = expect_context();
 
     view! {
@@ -237,7 +244,7 @@ fn WaitingForDoubleTargetView(double_card: Signal<CardPair>) -> impl IntoView {
             </Inactive>
             <Action slot>
                 <button
-                    prop:disabled=not_selected
+                    prop:disabled=move || not_selected() || error().is_some()
                     on:click=move |_| {
                         ws.get_value()
                             .send_game_input(
@@ -262,6 +269,7 @@ fn WaitingForDoubleTargetView(double_card: Signal<CardPair>) -> impl IntoView {
 
                     "Skip"
                 </button>
+                <RuleHintView error/>
             </Action>
         </Panel>
     }
@@ -272,6 +280,7 @@ fn WaitingForMarkedPriceView(#[prop(into)] target: Signal<AuctionTarget>) -> imp
     let ws: Ws = expect_context();
 
     let (price, set_price) = create_signal(0 as Money);
+    let error = use_rule_check(move || Some(ActionInput::AssignMarkedPrice(price())));
 
     view! {
         <Panel
@@ -287,12 +296,16 @@ fn WaitingForMarkedPriceView(#[prop(into)] target: Signal<AuctionTarget>) -> imp
                 <AuctionTargetView target/>
             </Inactive>
             <Action slot>
-                <button on:click=move |_| {
-                    ws.get_value().send_game_input(ActionInput::AssignMarkedPrice(price()))
-                }>
+                <button
+                    prop:disabled=move || error().is_some()
+                    on:click=move |_| {
+                        ws.get_value().send_game_input(ActionInput::AssignMarkedPrice(price()))
+                    }
+                >
 
                     "Confirm"
                 </button>
+                <RuleHintView error/>
             </Action>
         </Panel>
     }
@@ -309,6 +322,7 @@ fn FreeAuctionView(
     let ws: Ws = expect_context();
 
     let (price, set_price) = create_signal(0 as Money);
+    let error = use_rule_check(move || Some(ActionInput::Bid(price())));
 
     let player: Signal<Player> = expect_context();
     let is_host = Signal::derive(move || player().id == host());
@@ -365,9 +379,10 @@ fn FreeAuctionView(
             </Active>
             <Inactive slot>()</Inactive>
             <Action slot>
-                <button on:click=move |_| {
-                    ws.get_value().send_game_input(ActionInput::Bid(price()))
-                }>
+                <button
+                    prop:disabled=move || error().is_some()
+                    on:click=move |_| ws.get_value().send_game_input(ActionInput::Bid(price()))
+                >
 
                     "Make Offer"
                 </button>
@@ -380,6 +395,7 @@ fn FreeAuctionView(
                         "Make a Call"
                     </button>
                 </Show>
+                <RuleHintView error/>
             </Action>
         </Panel>
     }
@@ -395,6 +411,7 @@ fn MarkedAuctionView(
 
     let player: Signal<Player> = expect_context();
     let self_id = player.get_untracked().id;
+    let error = use_rule_check(|| Some(ActionInput::MarkedReaction(MarkedReactionInner::Accept)));
     view! {
         <Panel
             subview=SubView::MarkedAuction
@@ -410,10 +427,17 @@ fn MarkedAuctionView(
                 <PriceDisplayView pair=price/>
             </Inactive>
             <Action slot>
-                <button on:click=move |_| {
-                    ws.get_value()
-                        .send_game_input(ActionInput::MarkedReaction(MarkedReactionInner::Accept))
-                }>"Accept"</button>
+                <button
+                    prop:disabled=move || error().is_some()
+                    on:click=move |_| {
+                        ws.get_value()
+                            .send_game_input(
+                                ActionInput::MarkedReaction(MarkedReactionInner::Accept),
+                            )
+                    }
+                >
+                    "Accept"
+                </button>
                 <Show when=move || host() != self_id>
                     <button
                         class="secondary"
@@ -428,6 +452,7 @@ fn MarkedAuctionView(
                         "Pass"
                     </button>
                 </Show>
+                <RuleHintView error/>
             </Action>
         </Panel>
     }
@@ -439,6 +464,8 @@ fn CircleAuctionView(target: Signal<AuctionTarget>, current: Signal<MoneyPair>)
 
     let (price, set_price) = create_signal(0 as Money);
     create_effect(move |_| set_price(current().1));
+    let error =
+        use_rule_check(move || Some(ActionInput::BidOptional(BidOptionalInner::Bid(price()))));
     view! {
         <Panel
             subview=SubView::CircleAuction
@@ -456,10 +483,17 @@ fn CircleAuctionView(target: Signal<AuctionTarget>, current: Signal<MoneyPair>)
                 <AuctionTargetView target/>
             </Inactive>
             <Action slot>
-                <button on:click=move |_| {
-                    ws.get_value()
-                        .send_game_input(ActionInput::BidOptional(BidOptionalInner::Bid(price())))
-                }>"Make Offer"</button>
+                <button
+                    prop:disabled=move || error().is_some()
+                    on:click=move |_| {
+                        ws.get_value()
+                            .send_game_input(
+                                ActionInput::BidOptional(BidOptionalInner::Bid(price())),
+                            )
+                    }
+                >
+                    "Make Offer"
+                </button>
                 <button
                     class="secondary"
                     on:click=move |_| {
@@ -470,6 +504,7 @@ fn CircleAuctionView(target: Signal<AuctionTarget>, current: Signal<MoneyPair>)
 
                     "Pass"
                 </button>
+                <RuleHintView error/>
             </Action>
         </Panel>
     }
@@ -484,6 +519,7 @@ fn FistAuctionView(
     let ws: Ws = expect_context();
 
     let (price, set_price) = create_signal(0 as Money);
+    let error = use_rule_check(move || Some(ActionInput::Bid(price())));
 
     let game_state: RwSignal<GameState> = expect_context();
     let player_status = game_state
@@ -517,9 +553,12 @@ fn FistAuctionView(
             </Active>
             <Inactive slot>()</Inactive>
             <Action slot>
-                <button on:click=move |_| {
-                    ws.get_value().send_game_input(ActionInput::Bid(price()))
-                }>"Make Offer"</button>
+                <button
+                    prop:disabled=move || error().is_some()
+                    on:click=move |_| ws.get_value().send_game_input(ActionInput::Bid(price()))
+                >
+                    "Make Offer"
+                </button>
                 <Show when=is_host>
                     <button
                         class="contrast"
@@ -529,11 +568,28 @@ fn FistAuctionView(
                         "Complete Auction"
                     </button>
                 </Show>
+                <RuleHintView error/>
             </Action>
         </Panel>
     }
 }
 
+// Checks what a button would send against the shared rules, so it can be
+// greyed out with the reason instead of waiting on the server.
+fn use_rule_check(input: impl Fn() -> Option<ActionInput> + 'static) -> Signal<Option<GameError>> {
+    let game_state: RwSignal<GameState> = expect_context();
+    let player: Signal<Player> = expect_context();
+    Signal::derive(move || {
+        let input = input()?;
+        game_state.with(|state| state.check_input(player().id, &input).err())
+    })
+}
+
+#[component]
+fn RuleHintView(error: Signal<Option<GameError>>) -> impl IntoView {
+    view! { <small class="c-red self-center">{move || error().map(|error| error.to_string())}</small> }
+}
+
 #[slot]
 struct Active {
     children: ChildrenFn,
@@ -560,6 +616,9 @@ fn Panel(
 ) -> impl IntoView {
     let player_active: Signal<bool> = expect_context();
     let current_subview: Memo<SubView> = expect_context();
+    let pending: RwSignal<PendingInputs> = expect_context();
+    // hold the buttons until the server has answered the last action
+    let waiting = move || pending().0 > 0;
 
     // TODO: Too cringy to read it twice but can't think of a better way for it to work
     view! {
@@ -589,14 +648,18 @@ fn Panel(
 
                 </div>
                 <footer>
-                    <div class="flex flex-justify-center px-2 gap-2">
+                    <fieldset
+                        class="flex flex-justify-center px-2 gap-2 mb-0"
+                        prop:disabled=waiting
+                        aria-busy=move || waiting().to_string()
+                    >
 
                         {
                             let action = action.children.clone();
                             view! { <Show when=player_active>{action()}</Show> }
                         }
 
-                    </div>
+                    </fieldset>
                 </footer>
             </article>
         </Show>
@@ -657,4 +720,3 @@ fn PriceDisplayView(#[prop(into)] pair: Signal<MoneyPair>) -> impl IntoView {
         </div>
     }
 }
-
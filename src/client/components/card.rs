@@ -1,4 +1,8 @@
-use crate::common::card::{AuctionType, Card, CardColor};
+use crate::common::{
+    card::{AuctionType, Card, CardColor},
+    game_state::GameState,
+    player::Player,
+};
 use leptos::ev::DragEvent;
 use leptos::*;
 
@@ -15,6 +19,25 @@ pub(crate) fn CardView(
         Signal::derive(move || selected_card().is_some_and(|current| current.id == card.id));
 
     let dragging: RwSignal<bool> = expect_context();
+
+    // while it is our turn to put down a card, the ones the rules would refuse
+    // are greyed out with the reason
+    let game_state = use_context::<RwSignal<GameState>>();
+    let player = use_context::<Signal<Player>>();
+    let blocked = Signal::derive(move || {
+        let (Some(game_state), Some(player), true) = (game_state, player, selectable) else {
+            return None;
+        };
+        let player_id = player().id;
+        game_state.with(|state| {
+            let takes_card =
+                state.card_input(card.id).is_some() && state.stage.is_player_active(player_id);
+            takes_card
+                .then(|| state.check_card(player_id, card.id).err())
+                .flatten()
+        })
+    });
+
     let wrapper_class = move || {
         format!(
             "{} {}
@@ -51,7 +74,7 @@ pub(crate) fn CardView(
         dragging.set(false);
     };
     let on_click = move |_| {
-        if selectable {
+        if selectable && blocked.get_untracked().is_none() {
             if selected() {
                 selected_card.set(None);
             } else {
@@ -66,7 +89,10 @@ pub(crate) fn CardView(
             class=("cursor-context-menu", selected)
             class=("glow", move || selected() && !display_only)
             class=wrapper_class
-            prop:draggable=selectable
+            class=("grayscale", move || blocked().is_some())
+            class=("opacity-50", move || blocked().is_some())
+            title=move || blocked().map(|error| error.to_string())
+            prop:draggable=move || selectable && blocked().is_none()
             on:dragstart=on_dragstart
             on:dragend=on_dragend
             on:click=on_click
@@ -138,4 +164,3 @@ impl CardColor {
         }
     }
 }
-
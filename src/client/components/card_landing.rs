@@ -1,6 +1,7 @@
 use crate::client::components::card::{CardView, CARD_ID_FORMAT};
 use crate::common::card::Card;
 use crate::common::game_state::GameState;
+use crate::common::player::Player;
 use leptos::ev::DragEvent;
 use leptos::*;
 
@@ -9,6 +10,9 @@ pub fn CardLandingView() -> impl IntoView {
     let game_state: RwSignal<GameState> = expect_context();
     let selected_card: RwSignal<Option<Card>> = expect_context();
     let dragging: RwSignal<bool> = expect_context();
+    let player: Signal<Player> = expect_context();
+    // why the last card dropped here was turned away
+    let (rejection, set_rejection) = create_signal(None::<String>);
 
     let get_card = move |ev: DragEvent| {
         let card_id: usize = ev
@@ -43,7 +47,17 @@ pub fn CardLandingView() -> impl IntoView {
     let on_drop = move |ev: DragEvent| {
         ev.prevent_default();
         let card = get_card(ev);
-        selected_card.set(card);
+        let error = card.and_then(|card| {
+            game_state
+                .with_untracked(|state| state.check_card(player.get_untracked().id, card.id).err())
+        });
+        match error {
+            Some(error) => set_rejection(Some(error.to_string())),
+            None => {
+                set_rejection(None);
+                selected_card.set(card);
+            }
+        }
         set_hovering(false);
     };
 
@@ -59,8 +73,9 @@ pub fn CardLandingView() -> impl IntoView {
         }
         .into_view(),
         None => view! {
-            <div class="h-60% flex flex-justify-center flex-items-center">
+            <div class="h-60% flex flex-col flex-justify-center flex-items-center text-center">
                 <span>"drag to here"</span>
+                <small class="c-red px-2">{rejection}</small>
             </div>
         }
         .into_view(),
@@ -80,4 +95,3 @@ pub fn CardLandingView() -> impl IntoView {
         </div>
     }
 }
-
@@ -0,0 +1,39 @@
+use crate::client::websocket::ConnectionStatus;
+use leptos::*;
+use leptos_icons::{
+    BiIcon::{BiNoSignalRegular, BiSignal5Regular},
+    Icon,
+};
+
+#[component]
+pub fn ConnectionStatusView() -> impl IntoView {
+    let status: RwSignal<ConnectionStatus> = expect_context();
+    let online = move || status() == ConnectionStatus::Open;
+    let message = move || match status() {
+        ConnectionStatus::Open => String::new(),
+        ConnectionStatus::Connecting => "Connecting...".to_string(),
+        ConnectionStatus::Reconnecting { attempt } => format!("Reconnecting ({})...", attempt),
+        ConnectionStatus::Rejected => "Outdated".to_string(),
+        ConnectionStatus::Closed => "Offline".to_string(),
+    };
+    view! {
+        <div class="fixed top-4 right-4 z-10 flex flex-items-center gap-1" title=message>
+            <Show
+                when=online
+                fallback=|| {
+                    view! {
+                        <Icon
+                            icon=Icon::from(BiNoSignalRegular)
+                            width="24px"
+                            height="24px"
+                            class="c-red"
+                        />
+                    }
+                }
+            >
+                <Icon icon=Icon::from(BiSignal5Regular) width="24px" height="24px" class="c-green"/>
+            </Show>
+            <span class="font-size-3">{message}</span>
+        </div>
+    }
+}
@@ -12,19 +12,7 @@ pub fn GlobalInfoView() -> impl IntoView {
         .enumerate()
         .map(|(i, ())| {
             let color = CardColor::from_index(i);
-            let count = Signal::derive(move || {
-                game_state()
-                    .owned_cards
-                    .iter()
-                    .map(move |owned_cards| {
-                        owned_cards
-                            .iter()
-                            .filter(|card| card.color == color)
-                            .count()
-                    })
-                    .reduce(|x, y| x + y)
-                    .unwrap()
-            });
+            let count = Signal::derive(move || game_state().round_board.count(color));
             (color, count)
         })
         .collect::<Vec<(CardColor, Signal<usize>)>>()
@@ -2,32 +2,85 @@ use crate::{
     client::{
         components::{
             action_panel::{ActionPanelView, AuctionTargetView},
+            connection_status::ConnectionStatusView,
             ident_icon::PlayerIconView,
             money_display::MoneyDisplayView,
             player_hand::PlayerHandView,
+            players_row::PlayersRowView,
         },
-        context::inject_game_context,
+        context::{inject_game_context, Spectating},
     },
-    common::{game_state::GameState, server_message::GameEvent},
+    common::{game_state::AuctionTarget, input::ActionInput, server_message::GameEvent},
 };
 use leptos::*;
 use leptos_icons::{
     BiIcon::{BiArrowToRightSolid, BiBankSolid, BiNoSignalRegular, BiSignal5Regular},
     Icon,
 };
+use leptos_router::{use_params_map, A};
+use std::collections::VecDeque;
 
 #[derive(Clone)]
 pub struct EventModal {
-    event: Option<GameEvent>,
+    // shown one after the other, a round end alone brings three
+    events: VecDeque<GameEvent>,
+    toasts: Vec<(usize, String)>,
+    #[cfg(not(feature = "ssr"))]
+    next_toast: usize,
+    closed: Option<ClosedReason>,
+}
+
+// why this tab can no longer play
+#[derive(Clone, Copy, PartialEq)]
+pub enum ClosedReason {
+    TakenOver,
+    #[cfg_attr(feature = "ssr", allow(dead_code))]
+    GameStopped,
+    Outdated,
 }
 
 impl EventModal {
     fn new() -> RwSignal<Self> {
-        RwSignal::new(Self { event: None })
+        RwSignal::new(Self {
+            events: VecDeque::new(),
+            toasts: Vec::new(),
+            #[cfg(not(feature = "ssr"))]
+            next_toast: 0,
+            closed: None,
+        })
     }
 
+    #[cfg(not(feature = "ssr"))]
     pub fn show(&mut self, event: GameEvent) {
-        self.event = Some(event);
+        self.events.push_back(event);
+    }
+
+    fn current(&self) -> Option<GameEvent> {
+        self.events.front().cloned()
+    }
+
+    // moves on to the next event, if any
+    fn next(&mut self) {
+        self.events.pop_front();
+    }
+
+    // returns the id to dismiss it with
+    #[cfg(not(feature = "ssr"))]
+    pub fn toast(&mut self, message: String) -> usize {
+        let id = self.next_toast;
+        self.next_toast += 1;
+        self.toasts.push((id, message));
+        id
+    }
+
+    pub fn dismiss(&mut self, id: usize) {
+        self.toasts.retain(|(toast, _)| *toast != id);
+    }
+
+    #[cfg(not(feature = "ssr"))]
+    pub fn close(&mut self, reason: ClosedReason) {
+        self.events.clear();
+        self.closed = Some(reason);
     }
 }
 
@@ -36,20 +89,30 @@ pub fn InGameView() -> impl IntoView {
     let modal = EventModal::new();
     provide_context(modal);
 
-    inject_game_context();
+    let params = use_params_map();
+    let game_id =
+        params.with_untracked(|params| params.get("game_id").cloned().unwrap_or_default());
+    let loaded = inject_game_context(game_id);
+    let spectating: Signal<Spectating> = expect_context();
 
     view! {
         <Portal>
-            <dialog prop:open=move || modal().event.is_some()>
+            <dialog prop:open=move || !modal().events.is_empty()>
                 <article>
                     <header>
                         <a
                             class="close"
-                            on:click=move |_| { modal.update(|modal| modal.event = None) }
+                            on:click=move |_| { modal.update(|modal| modal.events.clear()) }
                         ></a>
-                        <p>"Notification"</p>
+                        <p>
+                            "Notification"
+                            {move || {
+                                let count = modal().events.len();
+                                (count > 1).then(|| format!(" ({} more)", count - 1))
+                            }}
+                        </p>
                     </header>
-                    {if let Some(event) = modal().event {
+                    {move || if let Some(event) = modal().current() {
                         match event {
                             GameEvent::PlayerDisconnect(player_id) => {
                                 view! {
@@ -111,13 +174,143 @@ pub fn InGameView() -> impl IntoView {
                                 }
                                     .into_view()
                             }
+                            GameEvent::DoubleAuctioneer {
+                                auctioneer,
+                                double_card,
+                                target_card,
+                            } => {
+                                let target = AuctionTarget::Double {
+                                    double_card,
+                                    target_card,
+                                };
+                                view! {
+                                    <div class="flex flex-justify-center flex-items-center">
+                                        <PlayerIconView id=auctioneer/>
+                                        <h3 class="ml-2 mb-0">"runs the auction for both cards."</h3>
+                                    </div>
+                                    <AuctionTargetView target/>
+                                }
+                                    .into_view()
+                            }
+                            GameEvent::DoubleReturned { owner } => {
+                                view! {
+                                    <div class="flex flex-justify-center flex-items-center">
+                                        <h3 class="mr-2 mb-0">"Nobody added a card, back to"</h3>
+                                        <PlayerIconView id=owner/>
+                                    </div>
+                                }
+                                    .into_view()
+                            }
+                            GameEvent::DoubleFreeGet { owner, card } => {
+                                view! {
+                                    <div class="flex flex-justify-center flex-items-center">
+                                        <PlayerIconView id=owner/>
+                                        <h3 class="ml-2 mb-0">"gets the double card for free."</h3>
+                                    </div>
+                                    <AuctionTargetView target=AuctionTarget::Single((owner, card))/>
+                                }
+                                    .into_view()
+                            }
+                            GameEvent::SealedBidsRevealed { host, bids } => {
+                                let bids = bids
+                                    .into_iter()
+                                    .enumerate()
+                                    .map(|(id, bid)| {
+                                        view! {
+                                            <div class="flex flex-col flex-items-center">
+                                                <PlayerIconView id active={id == host}/>
+                                                <MoneyDisplayView value=bid/>
+                                            </div>
+                                        }
+                                    })
+                                    .collect_view();
+                                view! {
+                                    <h3 class="text-center">"Sealed bids revealed."</h3>
+                                    <div class="flex flex-justify-center gap-4">{bids}</div>
+                                }
+                                    .into_view()
+                            }
+                            // never shown, see GameEvent::is_notable
+                            GameEvent::CardPlayed { .. }
+                            | GameEvent::DoubleTargetOffered { .. }
+                            | GameEvent::DoubleTargetPassed { .. }
+                            | GameEvent::MarkedPriceSet { .. }
+                            | GameEvent::BidPlaced { .. }
+                            | GameEvent::SealedBidPlaced { .. }
+                            | GameEvent::BidPassed { .. } => ().into_view(),
                             GameEvent::RoundEnd => {
                                 view! { <h3 class="text-center">"Round has ended."</h3> }
                                     .into_view()
                             }
+                            GameEvent::RoundScored { round, ranking, payouts } => {
+                                let ranking = ranking
+                                    .into_iter()
+                                    .map(|(color, value)| {
+                                        let outer_class = format!(
+                                            "{} w-8 h-8 rd-1 flex flex-justify-center flex-items-center",
+                                            color.main_bg(),
+                                        );
+                                        let inner_class = format!("{} varela", color.comp_fg());
+                                        view! {
+                                            <div class=outer_class>
+                                                <span class=inner_class>{value}</span>
+                                            </div>
+                                        }
+                                    })
+                                    .collect_view();
+                                let payouts = payouts
+                                    .into_iter()
+                                    .enumerate()
+                                    .map(|(id, earned)| {
+                                        view! {
+                                            <div class="flex flex-col flex-items-center">
+                                                <PlayerIconView id/>
+                                                <MoneyDisplayView value=earned/>
+                                            </div>
+                                        }
+                                    })
+                                    .collect_view();
+                                view! {
+                                    <h3 class="text-center">"Round " {round + 1} " scored."</h3>
+                                    <div class="flex flex-justify-center gap-2">{ranking}</div>
+                                    <div class="flex flex-justify-center gap-4 mt-4">{payouts}</div>
+                                }
+                                    .into_view()
+                            }
+                            GameEvent::CardsDealt { round, count } => {
+                                view! {
+                                    <h3 class="text-center">
+                                        "Round " {round + 1} ": everyone receives " {count} " cards."
+                                    </h3>
+                                }
+                                    .into_view()
+                            }
                             GameEvent::GameEnd => {
                                 view! { <h3 class="text-center">"Game has ended."</h3> }.into_view()
                             }
+                            GameEvent::AuctionCall { host, calls } => {
+                                let message = match calls {
+                                    1 => "Going once!",
+                                    2 => "Going twice!",
+                                    _ => "Sold!",
+                                };
+                                view! {
+                                    <div class="flex flex-justify-center flex-items-center">
+                                        <PlayerIconView id=host/>
+                                        <h3 class="ml-2 mb-0">{message}</h3>
+                                    </div>
+                                }
+                                    .into_view()
+                            }
+                            GameEvent::TurnTimeout { player, action } => {
+                                view! {
+                                    <div class="flex flex-col flex-justify-center flex-items-center">
+                                        <PlayerIconView id=player/>
+                                        <span>"Ran out of time: " {timeout_text(action)}</span>
+                                    </div>
+                                }
+                                    .into_view()
+                            }
                         }
                     } else {
                         ().into_view()
@@ -125,15 +318,101 @@ pub fn InGameView() -> impl IntoView {
 
                     <footer>
                         <button on:click=move |_| {
-                            modal.update(|modal| modal.event = None)
-                        }>"OK"</button>
+                            modal.update(|modal| modal.next())
+                        }>{move || if modal().events.len() > 1 { "Next" } else { "OK" }}</button>
                     </footer>
                 </article>
             </dialog>
         </Portal>
 
-        <ActionPanelView/>
-        <PlayerHandView/>
+        <ConnectionStatusView/>
+        <div class="fixed bottom-4 right-4 z-10 flex flex-col gap-2">
+            <For
+                each=move || modal().toasts
+                key=|(id, _)| *id
+                children=move |(id, message)| {
+                    view! {
+                        <article
+                            class="mb-0 py-2 px-4 c-red cursor-pointer"
+                            on:click=move |_| modal.update(|modal| modal.dismiss(id))
+                        >
+                            {message}
+                        </article>
+                    }
+                }
+            />
+        </div>
+
+        {move || match modal().closed {
+            Some(reason) => view! { <ClosedView reason/> }.into_view(),
+            None => {
+                view! {
+                    <Show
+                        when=loaded
+                        fallback=|| {
+                            view! { <article aria-busy="true">"Connecting to game..."</article> }
+                        }
+                    >
+                        <PlayersRowView/>
+                        <Show
+                            when=move || !spectating().0
+                            fallback=|| {
+                                view! { <article class="text-center">"Spectating"</article> }
+                            }
+                        >
+                            <ActionPanelView/>
+                            <PlayerHandView/>
+                        </Show>
+                    </Show>
+                }
+                    .into_view()
+            }
+        }}
+    }
+}
+
+#[component]
+fn ClosedView(reason: ClosedReason) -> impl IntoView {
+    let (title, message) = match reason {
+        ClosedReason::TakenOver => (
+            "Playing elsewhere",
+            "This game was opened in another tab or window, which has taken over.",
+        ),
+        ClosedReason::GameStopped => ("Game closed", "The server has closed this game."),
+        ClosedReason::Outdated => (
+            "Update available",
+            "The server has been updated, please reload the page to keep playing.",
+        ),
+    };
+    view! {
+        <article class="container text-center">
+            <h3>{title}</h3>
+            <p>{message}</p>
+            <footer class="flex flex-justify-center gap-2">
+                <Show when=move || reason == ClosedReason::TakenOver>
+                    <button on:click=|_| {
+                        let _ = window().location().reload();
+                    }>"Play here"</button>
+                </Show>
+                <Show when=move || reason == ClosedReason::Outdated>
+                    <button on:click=|_| {
+                        let _ = window().location().reload();
+                    }>"Reload"</button>
+                </Show>
+                <A href="/">"Back to lobby"</A>
+            </footer>
+        </article>
     }
 }
 
+fn timeout_text(action: ActionInput) -> &'static str {
+    match action {
+        ActionInput::PlayCard(_) => "played their first card.",
+        ActionInput::AssignMarkedPrice(_) => "set the price to 0.",
+        ActionInput::Bid(_) => "offered nothing.",
+        ActionInput::Call => "auction completed.",
+        ActionInput::PlayCardOptional(_)
+        | ActionInput::BidOptional(_)
+        | ActionInput::MarkedReaction(_) => "passed.",
+    }
+}
@@ -0,0 +1,105 @@
+use crate::client::{
+    components::ident_icon::IdentIconView,
+    context::{get_nickname, get_uuid},
+};
+use crate::server::room::{create_room, list_rooms, RoomInfo, RoomState, MAX_PLAYERS};
+use leptos::*;
+use leptos_router::*;
+use leptos_use::use_interval_fn;
+
+#[component]
+pub fn LobbyView() -> impl IntoView {
+    let uuid = get_uuid();
+    let (nickname, set_nickname) = get_nickname();
+    let (room_name, set_room_name) = create_signal(String::new());
+    let (error, set_error) = create_signal(None::<String>);
+
+    let rooms = create_resource(uuid, |uuid| async move {
+        list_rooms(uuid.unwrap_or_default()).await
+    });
+    use_interval_fn(move || rooms.refetch(), 2000);
+
+    let navigate = use_navigate();
+    let create = create_action(
+        move |(uuid, nickname, room_name): &(String, String, String)| {
+            create_room(uuid.clone(), nickname.clone(), room_name.clone())
+        },
+    );
+    create_effect(move |_| match create.value()() {
+        Some(Ok(room_id)) => navigate(&format!("/room/{}", room_id), Default::default()),
+        Some(Err(e)) => set_error(Some(e.to_string())),
+        None => {}
+    });
+
+    let create_disabled =
+        move || uuid().is_none() || nickname().trim().is_empty() || room_name().trim().is_empty();
+
+    let room_list = move || {
+        rooms().map(|result| match result {
+            Ok(rooms) if rooms.is_empty() => {
+                view! { <p class="text-center">"No rooms yet, create one!"</p> }.into_view()
+            }
+            Ok(rooms) => rooms
+                .into_iter()
+                .map(|room| view! { <RoomEntryView room/> })
+                .collect_view(),
+            Err(e) => view! { <p class="c-red">{e.to_string()}</p> }.into_view(),
+        })
+    };
+
+    view! {
+        <article class="container">
+            <header>
+                <span class="novcento">"Lobby"</span>
+            </header>
+            <div class="flex flex-items-center gap-2">
+                <IdentIconView name=nickname/>
+                <input
+                    type="text"
+                    placeholder="Your name"
+                    prop:value=nickname
+                    on:input=move |event| set_nickname(event_target_value(&event))
+                />
+            </div>
+            <div class="flex flex-items-center gap-2">
+                <input
+                    type="text"
+                    placeholder="Room name"
+                    prop:value=room_name
+                    on:input=move |event| set_room_name(event_target_value(&event))
+                />
+                <button
+                    class="w-fit"
+                    prop:disabled=create_disabled
+                    on:click=move |_| {
+                        create.dispatch((uuid().unwrap_or_default(), nickname(), room_name()))
+                    }
+                >
+                    "Create"
+                </button>
+            </div>
+            <Show when=move || error().is_some()>
+                <p class="c-red">{error}</p>
+            </Show>
+            <Transition fallback=move || view! { <span>"loading"</span> }>{room_list}</Transition>
+        </article>
+    }
+}
+
+#[component]
+fn RoomEntryView(room: RoomInfo) -> impl IntoView {
+    let status = match room.state {
+        RoomState::Waiting => "Waiting",
+        RoomState::Started => "Started",
+        RoomState::Ended => "Ended",
+    };
+    let href = format!("/room/{}", room.id);
+    view! {
+        <div class="flex flex-items-center flex-justify-between b-1 b-solid rd-1 px-4 my-2">
+            <span>{room.name}</span>
+            <span class="varela">{room.players.len()} "/" {MAX_PLAYERS}</span>
+            <span class="novcento">{status}</span>
+            <A href>"Open"</A>
+        </div>
+    }
+}
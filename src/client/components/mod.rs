@@ -4,13 +4,16 @@ pub mod card_deck;
 pub mod card_landing;
 pub mod card_list;
 pub mod color_count;
+pub mod connection_status;
 pub mod global_info;
 pub mod ident_icon;
 pub mod in_game;
+pub mod lobby;
 pub mod money_display;
 pub mod money_input;
 pub mod money_pile;
 pub mod player_hand;
 pub mod player_info;
 pub mod players_row;
+pub mod room;
 
@@ -1,10 +1,12 @@
 use crate::client::components::ident_icon::IdentIconView;
+use crate::client::context::SpectatorCount;
 use crate::common::{game_state::GameState, player::Player};
 use leptos::*;
 
 #[component]
 pub fn PlayersRowView() -> impl IntoView {
     let game_state: RwSignal<GameState> = expect_context();
+    let spectators: RwSignal<SpectatorCount> = expect_context();
     let players = move || {
         let game_state = game_state();
         let result: Vec<(Player, bool)> = game_state
@@ -27,6 +29,14 @@ pub fn PlayersRowView() -> impl IntoView {
                     <span>{pair.0.name}</span>
                 </div>
             </For>
+            <Show when=move || { spectators().0 > 0 }>
+                <small class="block text-center">
+                    {move || match spectators().0 {
+                        1 => "1 spectator".to_string(),
+                        count => format!("{} spectators", count),
+                    }}
+                </small>
+            </Show>
         </div>
     }
 }
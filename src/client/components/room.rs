@@ -0,0 +1,316 @@
+use crate::client::{
+    components::ident_icon::IdentIconView,
+    context::{get_nickname, get_uuid},
+};
+use crate::common::config::{presets, GameConfig, MAX_STARTING_MONEY};
+use crate::server::room::{
+    add_bot, get_room, join_room, leave_room, remove_bot, set_ready, set_room_config, start_room,
+    RoomInfo, RoomState, MAX_PLAYERS, MIN_PLAYERS,
+};
+use leptos::*;
+use leptos_router::*;
+use leptos_use::use_interval_fn;
+
+#[derive(Clone)]
+enum RoomAction {
+    Join,
+    Leave,
+    Ready(bool),
+    Start,
+    AddBot,
+    RemoveBot(usize),
+    SetConfig(GameConfig),
+}
+
+#[component]
+pub fn RoomView() -> impl IntoView {
+    let params = use_params_map();
+    let room_id = move || params.with(|params| params.get("room_id").cloned().unwrap_or_default());
+    let uuid = get_uuid();
+    let (nickname, _) = get_nickname();
+    let (error, set_error) = create_signal(None::<String>);
+
+    let room = create_resource(
+        move || (uuid(), room_id()),
+        |(uuid, room_id)| async move { get_room(uuid.unwrap_or_default(), room_id).await },
+    );
+    use_interval_fn(move || room.refetch(), 1000);
+
+    let action = create_action(move |action: &RoomAction| {
+        let uuid = uuid.get_untracked().unwrap_or_default();
+        let room_id = room_id();
+        let nickname = nickname.get_untracked();
+        let action = action.clone();
+        async move {
+            match action {
+                RoomAction::Join => join_room(uuid, nickname, room_id).await,
+                RoomAction::Leave => leave_room(uuid, room_id).await,
+                RoomAction::Ready(ready) => set_ready(uuid, room_id, ready).await,
+                RoomAction::Start => start_room(uuid, room_id).await,
+                RoomAction::AddBot => add_bot(uuid, room_id).await,
+                RoomAction::RemoveBot(index) => remove_bot(uuid, room_id, index).await,
+                RoomAction::SetConfig(config) => set_room_config(uuid, room_id, config).await,
+            }
+        }
+    });
+
+    let navigate = use_navigate();
+    create_effect(move |_| match action.value()() {
+        Some(Ok(())) => {
+            set_error(None);
+            room.refetch();
+        }
+        Some(Err(e)) => set_error(Some(e.to_string())),
+        None => {}
+    });
+
+    // everyone seated follows the room into the game once the host starts it
+    create_effect(move |_| {
+        if let Some(Ok(info)) = room() {
+            if info.state == RoomState::Started && info.self_index.is_some() {
+                navigate(&format!("/game/{}", info.id), Default::default());
+            }
+        }
+    });
+
+    let content = move || {
+        room().map(|result| match result {
+            Ok(info) => view! { <RoomDetailView info action/> }.into_view(),
+            Err(e) => view! {
+                <p class="c-red">{e.to_string()}</p>
+                <A href="/">"Back to lobby"</A>
+            }
+            .into_view(),
+        })
+    };
+
+    view! {
+        <article class="container">
+            <Transition fallback=move || view! { <span>"loading"</span> }>{content}</Transition>
+            <Show when=move || error().is_some()>
+                <p class="c-red">{error}</p>
+            </Show>
+        </article>
+    }
+}
+
+#[component]
+fn RoomDetailView(
+    info: RoomInfo,
+    action: Action<RoomAction, Result<(), ServerFnError>>,
+) -> impl IntoView {
+    let waiting = info.state == RoomState::Waiting;
+    let is_host = info.is_host();
+    let players = info
+        .players
+        .iter()
+        .enumerate()
+        .map(|(i, player)| {
+            let bot = player.bot;
+            let badge = if i == info.host {
+                "HOST"
+            } else if bot {
+                "BOT"
+            } else if player.ready {
+                "READY"
+            } else {
+                "WAITING"
+            };
+            view! {
+                <div class="inline-block text-center mx-2">
+                    <IdentIconView name=player.name.clone()/>
+                    <span class="block">{player.name.clone()}</span>
+                    <span class="block novcento" class=("c-green", i == info.host || player.ready)>
+                        {badge}
+                    </span>
+                    <Show when=move || is_host && waiting && bot>
+                        <a
+                            class="block cursor-pointer"
+                            on:click=move |_| action.dispatch(RoomAction::RemoveBot(i))
+                        >
+                            "Remove"
+                        </a>
+                    </Show>
+                </div>
+            }
+        })
+        .collect_view();
+
+    let started = info.state == RoomState::Started;
+    let watch_url = format!("/game/{}", info.id);
+    let joined = info.self_index.is_some();
+    let ready = info
+        .self_index
+        .is_some_and(|index| info.players[index].ready);
+    let can_start = info.can_start();
+    let full = info.players.len() >= MAX_PLAYERS;
+    let config = info.config.clone();
+
+    view! {
+        <header class="flex flex-justify-between">
+            <span>{info.name.clone()}</span>
+            <span class="varela">
+                {info.players.len()} "/" {MAX_PLAYERS}
+            </span>
+        </header>
+        <div class="flex flex-justify-center">{players}</div>
+        <RoomRulesView config editable=waiting && is_host action/>
+        <footer class="flex flex-justify-center gap-2">
+            <Show when=move || waiting && !joined>
+                <button prop:disabled=full on:click=move |_| action.dispatch(RoomAction::Join)>
+                    "Join"
+                </button>
+            </Show>
+            <Show when=move || waiting && joined && !is_host>
+                <button on:click=move |_| action.dispatch(RoomAction::Ready(!ready))>
+                    {if ready { "Not Ready" } else { "Ready" }}
+                </button>
+            </Show>
+            <Show when=move || waiting && is_host>
+                <button
+                    class="secondary"
+                    prop:disabled=full
+                    on:click=move |_| action.dispatch(RoomAction::AddBot)
+                >
+                    "Add Bot"
+                </button>
+            </Show>
+            <Show when=move || waiting && is_host>
+                <button
+                    class="contrast"
+                    prop:disabled=!can_start
+                    title=format!("{}-{} players, everyone ready", MIN_PLAYERS, MAX_PLAYERS)
+                    on:click=move |_| action.dispatch(RoomAction::Start)
+                >
+                    "Start Game"
+                </button>
+            </Show>
+            <Show when=move || waiting && joined>
+                <button class="secondary" on:click=move |_| action.dispatch(RoomAction::Leave)>
+                    "Leave"
+                </button>
+            </Show>
+            <Show when=move || started && !joined>
+                <A href=watch_url.clone()>"Watch"</A>
+            </Show>
+            <A href="/">"Back to lobby"</A>
+        </footer>
+    }
+}
+
+// the rules the game will be played by, the host picks a preset or tweaks it
+#[component]
+fn RoomRulesView(
+    config: GameConfig,
+    editable: bool,
+    action: Action<RoomAction, Result<(), ServerFnError>>,
+) -> impl IntoView {
+    let values = config
+        .round_values
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join("/");
+    let summary = format!(
+        "{} rounds, ${} each, a round ends at {} cards of a color, colors pay {}, free auctions wait {}s",
+        config.rounds,
+        config.starting_money,
+        config.round_end_count,
+        values,
+        config.free_auction_window
+    );
+    if !editable {
+        return view! { <p class="text-center">{summary}</p> }.into_view();
+    }
+
+    let presets = presets();
+    let selected = presets.iter().position(|(_, preset)| *preset == config);
+    let options = presets
+        .iter()
+        .enumerate()
+        .map(|(i, (name, _))| {
+            view! {
+                <option value=i.to_string() selected=selected == Some(i)>
+                    {*name}
+                </option>
+            }
+        })
+        .collect_view();
+    let pick_preset = move |ev| {
+        let preset = event_target_value(&ev)
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| presets.get(i).cloned());
+        if let Some((_, config)) = preset {
+            action.dispatch(RoomAction::SetConfig(config));
+        }
+    };
+    // applies one house rule on top of the current rules
+    let house_rule = |apply: fn(&mut GameConfig, &str) -> bool| {
+        let config = config.clone();
+        move |ev| {
+            let mut config = config.clone();
+            if apply(&mut config, &event_target_value(&ev)) {
+                action.dispatch(RoomAction::SetConfig(config));
+            }
+        }
+    };
+    let money = house_rule(|config, value| {
+        value
+            .parse()
+            .map(|money| config.starting_money = money)
+            .is_ok()
+    });
+    let end_count = house_rule(|config, value| {
+        value
+            .parse()
+            .map(|count| config.round_end_count = count)
+            .is_ok()
+    });
+    let window = house_rule(|config, value| {
+        value
+            .parse()
+            .map(|window| config.free_auction_window = window)
+            .is_ok()
+    });
+
+    view! {
+        <div class="grid">
+            <label>
+                "Rules"
+                <select on:change=pick_preset>
+                    {options}
+                    <option value="" disabled=true selected=selected.is_none()>
+                        "House rules"
+                    </option>
+                </select>
+            </label>
+            <label>
+                "Starting money"
+                <input
+                    type="number"
+                    min="1"
+                    max=MAX_STARTING_MONEY
+                    value=config.starting_money
+                    on:change=money
+                />
+            </label>
+            <label>
+                "Cards to end a round"
+                <input type="number" min="1" value=config.round_end_count on:change=end_count/>
+            </label>
+            <label>
+                "Free auction seconds"
+                <input
+                    type="number"
+                    min="0.5"
+                    step="0.5"
+                    value=config.free_auction_window
+                    on:change=window
+                />
+            </label>
+        </div>
+        <p class="text-center">{summary}</p>
+    }
+    .into_view()
+}
@@ -1,13 +1,23 @@
-use crate::client::websocket::WsInner;
-use crate::common::card::{AuctionType, Card, CardColor};
-use crate::common::game_state::{AuctionState, AuctionTarget, GameStage};
-use crate::common::input::GAME_WS_URL;
+use crate::client::websocket::{ConnectionStatus, PendingInputs, WsInner};
+use crate::common::game_state::GameState;
+use crate::common::placeholder::PlaceHolder;
 use crate::common::player::Player;
-use crate::common::{game_state::GameState, server_message::ServerMessage};
 use crate::server::player::get_new_uuid;
 use leptos::*;
 use leptos_use::storage::{use_local_storage, JsonCodec};
 
+// how long a toast stays up unless clicked away
+#[cfg(not(feature = "ssr"))]
+const TOAST_DURATION: std::time::Duration = std::time::Duration::from_secs(4);
+
+// how many people are watching the game
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpectatorCount(pub usize);
+
+// whether this tab is only watching, it has no seat at the table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spectating(pub bool);
+
 pub fn get_uuid() -> Signal<Option<String>> {
     let (uuid, set_uuid, _) = use_local_storage::<Option<String>, JsonCodec>("uuid");
     if uuid.get_untracked().is_none() {
@@ -19,56 +29,170 @@ pub fn get_uuid() -> Signal<Option<String>> {
     uuid
 }
 
+pub fn get_nickname() -> (Signal<String>, WriteSignal<String>) {
+    let (name, set_name, _) = use_local_storage::<String, JsonCodec>("name");
+    (name, set_name)
+}
+
 #[cfg(not(feature = "ssr"))]
-pub fn inject_game_context() {
+pub fn inject_game_context(game_id: String) -> Signal<bool> {
     // WARN: think twice before changing this type, as many components are
     // relying on the type to fetch from the context API
 
-    use super::components::in_game::EventModal;
-    let game_state = RwSignal::new(GameState::dummy());
+    use super::components::in_game::{ClosedReason, EventModal};
+    use crate::common::{input::GAME_WS_URL, server_message::ServerMessage};
+    let game_state = RwSignal::new(GameState::placeholder());
     provide_context(game_state);
     // use it with:
     // let game_state: RwSignal<GameState> = expect_context();
 
+    // components read the state when they are built, so hold them back until
+    // the server has sent a real one
+    let loaded = RwSignal::new(false);
+
     let uuid = get_uuid();
-    let player_id = game_state
-        .get_untracked()
-        .players
-        .iter()
-        .find(|player| player.uuid == uuid().unwrap())
-        .unwrap()
-        .id;
-    let player = Signal::derive(move || game_state().players[player_id].clone());
+    let player = Signal::derive(move || {
+        let uuid = uuid().unwrap_or_default();
+        game_state
+            .with(|state| {
+                state
+                    .players
+                    .iter()
+                    .find(|player| player.uuid == uuid)
+                    .cloned()
+            })
+            .unwrap_or_else(Player::placeholder)
+    });
     provide_context(player);
     // use it with:
     // let player: Signal<Player> = expect_context();
 
+    // observers get every uuid blanked, so nobody matches
+    let spectating = Signal::derive(move || {
+        let uuid = uuid().unwrap_or_default();
+        Spectating(
+            loaded() && game_state.with(|state| state.players.iter().all(|p| p.uuid != uuid)),
+        )
+    });
+    provide_context(spectating);
+    // use it with:
+    // let spectating: Signal<Spectating> = expect_context();
+
+    let spectators = RwSignal::new(SpectatorCount(0));
+    provide_context(spectators);
+    // use it with:
+    // let spectators: RwSignal<SpectatorCount> = expect_context();
+
     let balance = Signal::derive(move || game_state().money[0]);
     provide_context(balance);
     // use it with:
     // let balance: Signal<Money> = expect_context();
 
     let modal: RwSignal<EventModal> = expect_context();
-
-    let ws = WsInner::new(GAME_WS_URL);
-    ws.set_onmessage(move |message| match message {
-        ServerMessage::StateUpdate(state) => {
-            game_state.set(state);
-        }
-        ServerMessage::GameEvent(event) => {
-            modal.update(|modal| modal.show(event));
+    let toast = move |message: String| {
+        let id = modal.try_update(|modal| modal.toast(message));
+        if let Some(id) = id {
+            set_timeout(
+                move || modal.update(|modal| modal.dismiss(id)),
+                TOAST_DURATION,
+            );
         }
-        ServerMessage::Disconnect => {
-            todo!()
-        }
-        ServerMessage::GameStop => {
-            todo!()
+    };
+
+    let status = RwSignal::new(ConnectionStatus::Connecting);
+    provide_context(status);
+    // use it with:
+    // let status: RwSignal<ConnectionStatus> = expect_context();
+
+    let pending = RwSignal::new(PendingInputs(0));
+    provide_context(pending);
+    // use it with:
+    // let pending: RwSignal<PendingInputs> = expect_context();
+
+    let ws = store_value(WsInner::default());
+    create_effect(move |_| {
+        if let Some(uuid) = uuid() {
+            let url = format!("{}/{}/{}", GAME_WS_URL, game_id, uuid);
+            let inner = WsInner::new(&url);
+            // runs again after a reconnect, which resyncs the board
+            inner.set_onopen(move || ws.get_value().request_state());
+            // the socket may report back after the game view is gone
+            inner.set_onstatus(move |new| {
+                status.try_set(new);
+                if new == ConnectionStatus::Rejected {
+                    modal.try_update(|modal| modal.close(ClosedReason::Outdated));
+                }
+            });
+            inner.set_onpending(move |count| {
+                pending.try_set(PendingInputs(count));
+            });
+            inner.set_onmessage(move |message| match message {
+                ServerMessage::StateUpdate(state) => {
+                    let ended = state.ended;
+                    game_state.set(state);
+                    loaded.set(true);
+                    // nothing follows the final state, the server hangs up
+                    if ended {
+                        ws.get_value().close();
+                    }
+                }
+                ServerMessage::StateDelta(delta) => {
+                    let in_sync =
+                        game_state.with_untracked(|state| state.version == delta.base_version);
+                    if !in_sync {
+                        // missed an update somewhere, start over from a snapshot
+                        ws.get_value().request_state();
+                    } else if delta.ops.is_empty() {
+                        // nothing visible changed, do not wake every component
+                        game_state.update_untracked(|state| {
+                            state.apply(delta);
+                        });
+                    } else {
+                        game_state.update(|state| {
+                            state.apply(delta);
+                        });
+                        if game_state.with_untracked(|state| state.ended) {
+                            ws.get_value().close();
+                        }
+                    }
+                }
+                ServerMessage::GameEvent(event) => {
+                    if event.is_notable() {
+                        modal.update(|modal| modal.show(event));
+                    }
+                }
+                ServerMessage::Disconnect => {
+                    ws.get_value().close();
+                    modal.update(|modal| modal.close(ClosedReason::TakenOver));
+                }
+                ServerMessage::GameStop => {
+                    ws.get_value().close();
+                    modal.update(|modal| modal.close(ClosedReason::GameStopped));
+                }
+                ServerMessage::Ack { seq } => ws.get_value().settle(seq),
+                ServerMessage::Rejected { seq, error, input } => {
+                    ws.get_value().settle(seq);
+                    log::debug!("{:?} rejected with code {}", input, error.code());
+                    toast(error.to_string());
+                }
+                ServerMessage::SpectatorCount(count) => spectators.set(SpectatorCount(count)),
+                ServerMessage::Connected { last_seq } => ws.get_value().resume_after(last_seq),
+                ServerMessage::StringMessage(message) => toast(message),
+                ServerMessage::ProtocolError(message) => {
+                    log::error!("protocol error: {}", message);
+                    toast(format!("Protocol error: {}", message));
+                }
+            });
+            let previous = ws.get_value();
+            ws.set_value(inner);
+            previous.close();
         }
-        ServerMessage::StringMessage(message) => {
-            todo!()
+    });
+    on_cleanup(move || {
+        if let Some(ws) = ws.try_get_value() {
+            ws.close();
         }
     });
-    let ws = store_value(ws);
     provide_context(ws);
     // use it with:
     // let ws: RwSignal<Ws> = expect_context();
@@ -77,215 +201,39 @@ pub fn inject_game_context() {
     provide_context(dragging);
     // use it with:
     // let dragging: RwSignal<bool> = expect_context();
+
+    loaded.into()
 }
 
 #[cfg(feature = "ssr")]
-pub fn inject_game_context() {
-    let game_state = RwSignal::new(GameState::dummy());
+pub fn inject_game_context(_game_id: String) -> Signal<bool> {
+    let game_state = RwSignal::new(GameState::placeholder());
     provide_context(game_state);
 
-    let player = Signal::derive(move || game_state().players[0].clone());
+    let player = Signal::derive(Player::placeholder);
     provide_context(player);
 
-    let balance = Signal::derive(move || game_state().money[player.get_untracked().id]);
+    let spectating = Signal::derive(|| Spectating(false));
+    provide_context(spectating);
+
+    let spectators = RwSignal::new(SpectatorCount(0));
+    provide_context(spectators);
+
+    let balance = Signal::derive(move || game_state().money[0]);
     provide_context(balance);
 
-    let ws = WsInner::new(GAME_WS_URL);
-    let ws = store_value(ws);
+    let status = RwSignal::new(ConnectionStatus::Connecting);
+    provide_context(status);
+
+    let pending = RwSignal::new(PendingInputs(0));
+    provide_context(pending);
+
+    let ws = store_value(WsInner::default());
     provide_context(ws);
 
     let dragging: RwSignal<bool> = RwSignal::new(false);
     provide_context(dragging);
-}
-
-// TODO: remove this after test
-impl GameState {
-    fn dummy() -> Self {
-        #[cfg(feature = "ssr")]
-        let now = 114514 as f64;
 
-        #[cfg(not(feature = "ssr"))]
-        let now = js_sys::Date::now() / 1000.0;
-
-        let dummy_card1 = Card {
-            color: CardColor::Red,
-            ty: AuctionType::Free,
-            id: 101,
-        };
-        let dummy_card2 = Card {
-            color: CardColor::Red,
-            ty: AuctionType::Free,
-            id: 101,
-        };
-        GameState {
-            money: vec![514, 4, 51, 4, 19],
-            deck: vec![
-                vec![
-                    Card {
-                        color: CardColor::Red,
-                        ty: AuctionType::Free,
-                        id: 10,
-                    },
-                    Card {
-                        color: CardColor::Green,
-                        ty: AuctionType::Circle,
-                        id: 11,
-                    },
-                    Card {
-                        color: CardColor::Blue,
-                        ty: AuctionType::Marked,
-                        id: 12,
-                    },
-                    Card {
-                        color: CardColor::Purple,
-                        ty: AuctionType::Double,
-                        id: 13,
-                    },
-                    Card {
-                        color: CardColor::Red,
-                        ty: AuctionType::Free,
-                        id: 14,
-                    },
-                    Card {
-                        color: CardColor::Green,
-                        ty: AuctionType::Circle,
-                        id: 15,
-                    },
-                    Card {
-                        color: CardColor::Blue,
-                        ty: AuctionType::Marked,
-                        id: 16,
-                    },
-                    Card {
-                        color: CardColor::Purple,
-                        ty: AuctionType::Double,
-                        id: 17,
-                    },
-                ],
-                vec![],
-                vec![],
-                vec![],
-                vec![],
-            ],
-            players: vec![
-                Player {
-                    uuid: "pxwjh5L9FXOl".to_string(),
-                    id: 0,
-                    name: "Player0".into(),
-                    connected: true,
-                },
-                Player {
-                    uuid: 2.to_string(),
-                    id: 1,
-                    name: "Player1".into(),
-                    connected: true,
-                },
-                Player {
-                    uuid: 3.to_string(),
-                    id: 2,
-                    name: "Player2".into(),
-                    connected: true,
-                },
-                Player {
-                    uuid: 4.to_string(),
-                    id: 3,
-                    name: "Player3".into(),
-                    connected: true,
-                },
-                Player {
-                    uuid: 5.to_string(),
-                    id: 4,
-                    name: "Player4".into(),
-                    connected: true,
-                },
-            ],
-            // stage: GameStage::WaitingForDoubleTarget {
-            //     double_card: dummy_card1,
-            //     starter: 1,
-            //     current: 0,
-            // },
-            // stage: GameStage::WaitingForNextCard(0),
-            // stage: GameStage::WaitingForDoubleTarget {
-            //     double_card: (0, dummy_card1),
-            //     current: 0,
-            // },
-            // stage: GameStage::WaitingForMarkedPrice {
-            //     starter: 0,
-            //     target: AuctionTarget::Double {
-            //         double_card: (1, dummy_card2),
-            //         target_card: (0, dummy_card1),
-            //     },
-            // },
-            // stage: GameStage::AuctionInAction {
-            //     state: AuctionState::Free {
-            //         host: 1,
-            //         highest: (0, 114),
-            //         time_end: now + 3f64,
-            //         calls: 2,
-            //     },
-            //     target: AuctionTarget::Single((0, dummy_card1)),
-            // },
-            // stage: GameStage::AuctionInAction {
-            //     state: AuctionState::Marked {
-            //         price: (0, 114),
-            //         current: 0,
-            //     },
-            //     target: AuctionTarget::Single((0, dummy_card1)),
-            // },
-            // stage: GameStage::AuctionInAction {
-            //     state: AuctionState::Circle {
-            //         starter: 0,
-            //         current_player: 0,
-            //         highest: (0, 114),
-            //     },
-            //     target: AuctionTarget::Single((0, dummy_card1)),
-            // },
-            stage: GameStage::AuctionInAction {
-                state: AuctionState::Fist {
-                    host: 0,
-                    bids: vec![0, 0, 0, 0, 0],
-                    action_taken: vec![false, false, false, false, false],
-                },
-                target: AuctionTarget::Single((0, dummy_card1)),
-            },
-            current_round: 0,
-            values: [
-                [30, 20, 10, 0, 0],
-                [30, 20, 10, 0, 0],
-                [30, 20, 10, 0, 0],
-                [30, 20, 10, 0, 0],
-                [30, 20, 10, 0, 0],
-            ],
-            pool: Vec::new(),
-            owned_cards: vec![
-                vec![Card {
-                    color: CardColor::Yellow,
-                    ty: AuctionType::Marked,
-                    id: 1,
-                }],
-                vec![Card {
-                    color: CardColor::Yellow,
-                    ty: AuctionType::Marked,
-                    id: 2,
-                }],
-                vec![Card {
-                    color: CardColor::Yellow,
-                    ty: AuctionType::Marked,
-                    id: 3,
-                }],
-                vec![Card {
-                    color: CardColor::Yellow,
-                    ty: AuctionType::Marked,
-                    id: 4,
-                }],
-                vec![Card {
-                    color: CardColor::Yellow,
-                    ty: AuctionType::Marked,
-                    id: 5,
-                }],
-            ],
-            ended: false,
-        }
-    }
+    // nothing to render until the socket is up on the client
+    Signal::derive(|| false)
 }
-
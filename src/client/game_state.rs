@@ -1,6 +1,9 @@
 use crate::common::{
     card::Card,
-    game_state::{GameState, Money},
+    error::GameError,
+    game_state::{GameStage, GameState, Money},
+    input::{ActionInput, CardID, PlayCardOptionalInner},
+    player::PlayerID,
 };
 
 impl GameState {
@@ -11,6 +14,23 @@ impl GameState {
     pub(self) fn get_money(&self) -> &Money {
         self.money.get(0).unwrap()
     }
+
+    // the input dropping a card from the hand makes, if the stage takes a card
+    pub(crate) fn card_input(&self, card_id: CardID) -> Option<ActionInput> {
+        match self.stage {
+            GameStage::WaitingForNextCard(_) => Some(ActionInput::PlayCard(card_id)),
+            GameStage::WaitingForDoubleTarget { .. } => Some(ActionInput::PlayCardOptional(
+                PlayCardOptionalInner::Play(card_id),
+            )),
+            _ => None,
+        }
+    }
+
+    // checked against the shared rules before sending, the server still has the last word
+    pub(crate) fn check_card(&self, player: PlayerID, card_id: CardID) -> Result<(), GameError> {
+        let input = self.card_input(card_id).ok_or(GameError::InvalidAction)?;
+        self.check_input(player, &input)
+    }
 }
 
 pub(crate) type MoneySplit = [Money; 6];
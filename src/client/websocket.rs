@@ -2,88 +2,317 @@
 // had to modify leptos-use/use_websocket, so that it works with rykv
 
 use crate::common::{
-    input::{ActionInput, GameInput},
+    handshake::{Hello, HelloReply, CAP_STATE_DELTA, PROTOCOL_VERSION},
+    input::{ActionInput, GameInput, Seq, SERVER_SEQ},
     server_message::ServerMessage,
 };
 use cfg_if::cfg_if;
 use leptos::*;
 use rkyv::{from_bytes, to_bytes};
+use std::{cell::RefCell, rc::Rc};
 use wasm_bindgen::{closure::Closure, JsCast};
 use web_sys::{MessageEvent, WebSocket};
 
-#[derive(Clone)]
+// first retry after this many milliseconds, doubling up to the cap
+const RECONNECT_BASE_MS: u32 = 500;
+const RECONNECT_MAX_MS: u32 = 30_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    Connecting,
+    Open,
+    // waiting to try again, `attempt` counts from 1
+    Reconnecting { attempt: u32 },
+    // the server speaks another protocol, only a reload helps
+    Rejected,
+    Closed,
+}
+
+// Cloning shares the connection, so a socket replaced after a drop is seen by
+// every handle stored in the context.
+#[derive(Clone, Default)]
 pub struct WsInner {
-    inner: Option<WebSocket>,
+    inner: Rc<RefCell<Connection>>,
+}
+
+#[derive(Default)]
+struct Connection {
+    url: String,
+    socket: Option<WebSocket>,
+    attempts: u32,
+    // the server accepted our Hello on the current socket
+    handshaken: bool,
+    next_seq: Seq,
+    // actions the server has not answered yet, with their frames; sent again on
+    // every new socket, the server answers a resend without applying it twice
+    pending: Vec<(Seq, Vec<u8>)>,
+    // closed on purpose, do not come back
+    closed: bool,
+    onopen: Option<Rc<dyn Fn()>>,
+    onmessage: Option<Rc<dyn Fn(ServerMessage)>>,
+    onstatus: Option<Rc<dyn Fn(ConnectionStatus)>>,
+    onpending: Option<Rc<dyn Fn(usize)>>,
 }
 
 pub type Ws = StoredValue<WsInner>;
 
+// how many sent actions the server has not answered yet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PendingInputs(pub usize);
+
 impl WsInner {
-    #[cfg(not(feature = "ssr"))]
     pub fn new(url: &str) -> Self {
-        let url = normalize_url(url);
-        let ws = if let Ok(ws) = WebSocket::new(&url) {
-            ws
-        } else {
-            return WsInner { inner: None };
-        };
+        let ws = WsInner::default();
+        {
+            let mut inner = ws.inner.borrow_mut();
+            inner.url = normalize_url(url);
+            // until the server tells us where the seat left off
+            inner.next_seq = SERVER_SEQ + 1;
+        }
+        ws.connect();
+        ws
+    }
 
+    #[cfg(not(feature = "ssr"))]
+    fn connect(&self) {
+        let url = self.inner.borrow().url.clone();
+        let ws = match WebSocket::new(&url) {
+            Ok(ws) => ws,
+            Err(_) => {
+                self.schedule_reconnect();
+                return;
+            }
+        };
         ws.set_binary_type(web_sys::BinaryType::Arraybuffer);
 
-        WsInner { inner: Some(ws) }
+        let this = self.clone();
+        let onopen = Closure::wrap(Box::new(move |_: web_sys::Event| {
+            let hello = Hello {
+                version: PROTOCOL_VERSION,
+                capabilities: CAP_STATE_DELTA,
+            };
+            let data = to_bytes::<_, 16>(&hello).unwrap();
+            if let Some(ws) = this.inner.borrow().socket.as_ref() {
+                let _ = ws.send_with_u8_array(data.as_slice());
+            }
+        }) as Box<dyn FnMut(web_sys::Event)>);
+        ws.set_onopen(Some(onopen.as_ref().unchecked_ref()));
+        // the socket outlives this scope, so the closures must too
+        onopen.forget();
+
+        let this = self.clone();
+        let onmessage = Closure::wrap(Box::new(move |event: MessageEvent| {
+            let Ok(array_buffer) = event.data().dyn_into::<js_sys::ArrayBuffer>() else {
+                log::error!("received string instead of bytes");
+                return;
+            };
+            let array = js_sys::Uint8Array::new(&array_buffer).to_vec();
+            if !this.inner.borrow().handshaken {
+                this.finish_handshake(&array);
+                return;
+            }
+            let callback = this.inner.borrow().onmessage.clone();
+            match from_bytes::<ServerMessage>(&array) {
+                Ok(message) => {
+                    if let Some(callback) = callback {
+                        callback(message);
+                    }
+                }
+                Err(_) => log::error!("could not decode a message from the server"),
+            }
+        }) as Box<dyn FnMut(MessageEvent)>);
+        ws.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+        onmessage.forget();
+
+        let this = self.clone();
+        let onclose = Closure::wrap(Box::new(move |_: web_sys::Event| {
+            this.schedule_reconnect();
+        }) as Box<dyn FnMut(web_sys::Event)>);
+        ws.set_onclose(Some(onclose.as_ref().unchecked_ref()));
+        onclose.forget();
+
+        {
+            let mut inner = self.inner.borrow_mut();
+            inner.socket = Some(ws);
+            inner.handshaken = false;
+        }
+        self.set_status(ConnectionStatus::Connecting);
     }
 
     #[cfg(feature = "ssr")]
-    pub fn new(url: &str) -> Self {
-        WsInner { inner: None }
+    fn connect(&self) {}
+
+    // the first frame on every socket answers our Hello
+    fn finish_handshake(&self, data: &[u8]) {
+        let reply = from_bytes::<HelloReply>(data).unwrap_or_else(|_| HelloReply {
+            version: 0,
+            accepted: false,
+            message: "Unrecognized handshake reply.".to_string(),
+        });
+        if !reply.accepted {
+            log::error!(
+                "server (protocol {}) turned us away: {}",
+                reply.version,
+                reply.message
+            );
+            self.clone().close();
+            self.set_status(ConnectionStatus::Rejected);
+            return;
+        }
+        let (frames, callback) = {
+            let mut inner = self.inner.borrow_mut();
+            inner.handshaken = true;
+            inner.attempts = 0;
+            let frames: Vec<Vec<u8>> = inner.pending.iter().map(|(_, data)| data.clone()).collect();
+            (frames, inner.onopen.clone())
+        };
+        self.set_status(ConnectionStatus::Open);
+        if let Some(callback) = callback {
+            callback();
+        }
+        for data in frames {
+            self.send(&data);
+        }
+    }
+
+    fn schedule_reconnect(&self) {
+        let attempt = {
+            let mut inner = self.inner.borrow_mut();
+            if inner.closed {
+                return;
+            }
+            inner.socket = None;
+            inner.handshaken = false;
+            inner.attempts += 1;
+            inner.attempts
+        };
+        self.set_status(ConnectionStatus::Reconnecting { attempt });
+        let delay = RECONNECT_BASE_MS
+            .saturating_mul(1 << (attempt - 1).min(16))
+            .min(RECONNECT_MAX_MS);
+        let this = self.clone();
+        set_timeout(
+            move || {
+                if !this.inner.borrow().closed {
+                    this.connect();
+                }
+            },
+            std::time::Duration::from_millis(delay as u64),
+        );
+    }
+
+    fn set_status(&self, status: ConnectionStatus) {
+        let callback = self.inner.borrow().onstatus.clone();
+        if let Some(callback) = callback {
+            callback(status);
+        }
     }
 
     pub fn ready(&self) -> bool {
-        self.inner
-            .as_ref()
-            .is_some_and(|ws| ws.ready_state() == WebSocket::OPEN)
+        let inner = self.inner.borrow();
+        inner.handshaken
+            && inner
+                .socket
+                .as_ref()
+                .is_some_and(|ws| ws.ready_state() == WebSocket::OPEN)
     }
 
     fn send(&self, data: &[u8]) {
-        if let Some(ws) = self.inner.as_ref() {
-            if ws.ready_state() == WebSocket::OPEN {
-                log::debug!("sending: {:?}", data);
-                ws.send_with_u8_array(data);
+        if !self.ready() {
+            return;
+        }
+        if let Some(ws) = self.inner.borrow().socket.as_ref() {
+            log::debug!("sending: {:?}", data);
+            // a failed send closes the socket, the reconnect sends it again
+            if let Err(error) = ws.send_with_u8_array(data) {
+                log::warn!("could not send a frame: {:?}", error);
             }
         }
     }
 
+    // sent right away, or as soon as the socket is back
     pub fn send_game_input(&self, input: ActionInput) {
-        if self.ready() {
-            let input = GameInput::Action(input);
-            let data = to_bytes::<_, 4>(&input).unwrap();
-            self.send(data.as_slice());
+        let data = {
+            let mut inner = self.inner.borrow_mut();
+            if inner.closed {
+                return;
+            }
+            let seq = inner.next_seq;
+            inner.next_seq += 1;
+            let data = to_bytes::<_, 4>(&GameInput::Action { seq, input })
+                .unwrap()
+                .to_vec();
+            inner.pending.push((seq, data.clone()));
+            data
+        };
+        self.notify_pending();
+        self.send(&data);
+    }
+
+    // the server answered `seq`, either way it is no longer pending
+    pub fn settle(&self, seq: Seq) {
+        self.inner
+            .borrow_mut()
+            .pending
+            .retain(|(pending, _)| *pending != seq);
+        self.notify_pending();
+    }
+
+    // Numbers new actions after the last one the server has seen from this seat,
+    // whatever this tab counted before.
+    pub fn resume_after(&self, last_seq: Seq) {
+        let mut inner = self.inner.borrow_mut();
+        inner.next_seq = inner.next_seq.max(last_seq + 1);
+    }
+
+    fn notify_pending(&self) {
+        let (count, callback) = {
+            let inner = self.inner.borrow();
+            (inner.pending.len(), inner.onpending.clone())
+        };
+        if let Some(callback) = callback {
+            callback(count);
         }
     }
 
+    // not queued, every reconnect asks for the state anyway
+    pub fn request_state(&self) {
+        let data = to_bytes::<_, 4>(&GameInput::RequestState).unwrap();
+        self.send(data.as_slice());
+    }
+
+    // also runs after every reconnect
+    pub fn set_onopen(&self, callback: impl Fn() + 'static) {
+        self.inner.borrow_mut().onopen = Some(Rc::new(callback));
+    }
+
     pub fn set_onmessage(&self, callback: impl Fn(ServerMessage) + 'static) {
-        if let Some(ws) = self.inner.as_ref() {
-            let wrap = Closure::wrap(Box::new(move |event: MessageEvent| {
-                event.data().dyn_into::<js_sys::ArrayBuffer>().map_or_else(
-                    |_| {
-                        panic!("Received string instead of bytes.");
-                    },
-                    |array_buffer| {
-                        let array = js_sys::Uint8Array::new(&array_buffer);
-                        let array = array.to_vec();
-                        let data = from_bytes::<ServerMessage>(&array).unwrap();
-                        callback(data);
-                    },
-                );
-            }) as Box<dyn FnMut(MessageEvent)>);
-            ws.set_onmessage(Some(wrap.as_ref().unchecked_ref()));
-        }
+        self.inner.borrow_mut().onmessage = Some(Rc::new(callback));
+    }
+
+    // called with the number of unanswered actions whenever it changes
+    pub fn set_onpending(&self, callback: impl Fn(usize) + 'static) {
+        self.inner.borrow_mut().onpending = Some(Rc::new(callback));
+    }
+
+    pub fn set_onstatus(&self, callback: impl Fn(ConnectionStatus) + 'static) {
+        self.inner.borrow_mut().onstatus = Some(Rc::new(callback));
     }
 
     pub fn close(self) {
-        if let Some(ws) = self.inner {
-            ws.close();
+        let socket = {
+            let mut inner = self.inner.borrow_mut();
+            inner.closed = true;
+            inner.pending.clear();
+            inner.socket.take()
+        };
+        self.notify_pending();
+        if let Some(ws) = socket {
+            if let Err(error) = ws.close() {
+                log::warn!("could not close the socket: {:?}", error);
+            }
         }
+        self.set_status(ConnectionStatus::Closed);
     }
 }
 
@@ -127,4 +356,3 @@ fn detect_protocol() -> String {
         window().location().protocol().expect("Protocol not found").replace("http", "ws")
     }}
 }
-
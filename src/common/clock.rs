@@ -0,0 +1,43 @@
+use std::cell::Cell;
+
+// Where the rules engine gets its time from, in seconds since UNIX epoch.
+// Swap it out to drive free auctions deterministically in replays, bots and tests.
+pub trait Clock {
+    fn now(&self) -> f64;
+}
+
+// always reads the same instant, used to replay a logged input
+#[derive(Clone, Copy)]
+pub struct FixedClock(pub f64);
+
+impl Clock for FixedClock {
+    fn now(&self) -> f64 {
+        self.0
+    }
+}
+
+// only moves when told to
+#[derive(Default)]
+pub struct ManualClock {
+    now: Cell<f64>,
+}
+
+impl ManualClock {
+    #[cfg(feature = "ssr")]
+    pub fn new(start: f64) -> Self {
+        Self {
+            now: Cell::new(start),
+        }
+    }
+
+    #[cfg(feature = "ssr")]
+    pub fn advance(&self, seconds: f64) {
+        self.now.set(self.now.get() + seconds);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> f64 {
+        self.now.get()
+    }
+}
@@ -0,0 +1,287 @@
+#[cfg(feature = "ssr")]
+use super::card::{AuctionType, Card, CardColor};
+use super::game_state::Money;
+use serde::{Deserialize, Serialize};
+
+pub const MIN_PLAYERS: usize = 3;
+pub const MAX_PLAYERS: usize = 5;
+pub const MAX_STARTING_MONEY: Money = 10_000;
+// what one color may be worth in a single round
+const MAX_ROUND_VALUE: Money = 1_000;
+// cards of one color and auction type
+const MAX_CARDS_PER_KIND: usize = 10;
+
+#[cfg(feature = "ssr")]
+// the columns of `GameConfig::distribution`, cards are numbered in this order
+const AUCTION_TYPES: [AuctionType; 5] = [
+    AuctionType::Free,
+    AuctionType::Fist,
+    AuctionType::Marked,
+    AuctionType::Circle,
+    AuctionType::Double,
+];
+
+// The rules a game is played by. Picked in the room before the game starts and
+// saved with it, so a replay deals and scores exactly like the live game did.
+#[derive(
+    Clone,
+    Debug,
+    PartialEq,
+    Serialize,
+    Deserialize,
+    rkyv::Archive,
+    rkyv::Deserialize,
+    rkyv::Serialize,
+)]
+#[archive(check_bytes)]
+pub struct GameConfig {
+    // what every player starts the game with, paid in by the bank
+    pub starting_money: Money,
+    pub rounds: usize,
+    // a round ends as soon as this many cards of one color have been played
+    pub round_end_count: usize,
+    // what the most played colors of a round are worth, best first
+    pub round_values: Vec<Money>,
+    // seconds a free auction stays open after each bid
+    pub free_auction_window: f64,
+    // how many cards there are per color index and auction type
+    //        | Free | Fist | Marked | Circle | Double |
+    // Purple:| 3    | 2    | 2      | 3      | 2      |
+    // Blue:  | 3    | 3    | 3      | 2      | 2      |
+    // Red:   | 3    | 3    | 3      | 3      | 2      |
+    // Green: | 3    | 3    | 3      | 3      | 3      |
+    // Yellow:| 4    | 3    | 3      | 3      | 3      |
+    pub distribution: [[usize; 5]; 5],
+    // Vec<(player count, cards dealt to each player at the start of each round)>
+    pub deal_schedule: Vec<(usize, Vec<usize>)>,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            starting_money: 100,
+            rounds: 4,
+            round_end_count: 5,
+            round_values: vec![30, 20, 10],
+            free_auction_window: 3.0,
+            distribution: [
+                [3, 2, 2, 3, 2],
+                [3, 3, 3, 2, 2],
+                [3, 3, 3, 3, 2],
+                [3, 3, 3, 3, 3],
+                [4, 3, 3, 3, 3],
+            ],
+            deal_schedule: vec![
+                (3, vec![10, 6, 6, 0]),
+                (4, vec![9, 4, 4, 0]),
+                (5, vec![8, 3, 3, 0]),
+            ],
+        }
+    }
+}
+
+// what the host can pick from in the room, the first one is the default
+pub fn presets() -> Vec<(&'static str, GameConfig)> {
+    let standard = GameConfig::default();
+    // the last round deals nothing anyway, so dropping it keeps every card
+    let short = GameConfig {
+        rounds: 3,
+        deal_schedule: standard
+            .deal_schedule
+            .iter()
+            .map(|(players, deals)| (*players, deals[..3].to_vec()))
+            .collect(),
+        ..standard.clone()
+    };
+    let rich = GameConfig {
+        starting_money: 200,
+        round_values: vec![60, 40, 20],
+        ..standard.clone()
+    };
+    let quick = GameConfig {
+        round_end_count: 4,
+        free_auction_window: 2.0,
+        ..standard.clone()
+    };
+    vec![
+        ("Standard", standard),
+        ("Three rounds", short),
+        ("High stakes", rich),
+        ("Quick rounds", quick),
+    ]
+}
+
+impl GameConfig {
+    #[cfg(feature = "ssr")]
+    // every card in the game, numbered from 1 by color index and auction type
+    pub(crate) fn cards(&self) -> Vec<Card> {
+        let mut cards = Vec::new();
+        for (color, counts) in self.distribution.iter().enumerate() {
+            for (ty, count) in AUCTION_TYPES.iter().zip(counts) {
+                for _ in 0..*count {
+                    cards.push(Card {
+                        id: cards.len() + 1,
+                        color: CardColor::from_index(color),
+                        ty: *ty,
+                    });
+                }
+            }
+        }
+        cards
+    }
+
+    #[cfg(feature = "ssr")]
+    // how many cards each player gets at the start of `round`, counting from 0
+    pub(crate) fn cards_to_deal(&self, player_count: usize, round: usize) -> usize {
+        self.deal_schedule
+            .iter()
+            .find(|(count, _)| *count == player_count)
+            .and_then(|(_, rounds)| rounds.get(round))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    #[cfg(feature = "ssr")]
+    pub(crate) fn is_last_round(&self, round: usize) -> bool {
+        round + 1 >= self.rounds
+    }
+
+    // whether a game for `player_count` players can be played by these rules
+    pub fn validate(&self, player_count: usize) -> Result<(), String> {
+        if !(MIN_PLAYERS..=MAX_PLAYERS).contains(&player_count) {
+            return Err(format!(
+                "Need {}-{} players, not {}.",
+                MIN_PLAYERS, MAX_PLAYERS, player_count
+            ));
+        }
+        // round values are kept per color for at most five rounds
+        if !(1..=5).contains(&self.rounds) {
+            return Err("A game has 1-5 rounds.".to_string());
+        }
+        if !(1..=5).contains(&self.round_values.len()) {
+            return Err("Between one and five colors have to score each round.".to_string());
+        }
+        if self.round_end_count == 0 {
+            return Err("A round has to end after at least one card.".to_string());
+        }
+        if !(1..=MAX_STARTING_MONEY).contains(&self.starting_money) {
+            return Err(format!("Players start with $1-{}.", MAX_STARTING_MONEY));
+        }
+        if self
+            .round_values
+            .iter()
+            .any(|value| *value > MAX_ROUND_VALUE)
+        {
+            return Err(format!(
+                "A color is worth at most ${} a round.",
+                MAX_ROUND_VALUE
+            ));
+        }
+        if self
+            .distribution
+            .iter()
+            .flatten()
+            .any(|count| *count > MAX_CARDS_PER_KIND)
+        {
+            return Err(format!(
+                "There are at most {} cards of each color and auction type.",
+                MAX_CARDS_PER_KIND
+            ));
+        }
+        if !(0.5..=60.0).contains(&self.free_auction_window) {
+            return Err("Free auctions stay open for 0.5-60 seconds.".to_string());
+        }
+        let Some((_, deals)) = self
+            .deal_schedule
+            .iter()
+            .find(|(count, _)| *count == player_count)
+        else {
+            return Err(format!("No cards are dealt to {} players.", player_count));
+        };
+        if deals.len() != self.rounds {
+            return Err(format!(
+                "The deal schedule has {} rounds, not {}.",
+                deals.len(),
+                self.rounds
+            ));
+        }
+        let total = self.distribution.iter().flatten().sum::<usize>();
+        let dealt = deals.iter().try_fold(0usize, |dealt, deal| {
+            dealt.checked_add(deal.checked_mul(player_count)?)
+        });
+        match dealt {
+            Some(dealt) if dealt <= total => {}
+            _ => {
+                return Err(format!(
+                    "Dealing takes more cards than the {} there are.",
+                    total
+                ))
+            }
+        }
+        // at worst every card is sold in the last round, worth the best value of
+        // every round, on top of what the players started with
+        let best = self.round_values.iter().max().copied().unwrap_or(0);
+        let most_issued = (self.starting_money as u64 * player_count as u64)
+            + (total as u64 * self.rounds as u64 * best as u64);
+        if most_issued > Money::MAX as u64 {
+            return Err("The game could pay out more money than it can count.".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_are_valid_for_every_table() {
+        for (name, config) in presets() {
+            for players in MIN_PLAYERS..=MAX_PLAYERS {
+                assert_eq!(config.validate(players), Ok(()), "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn the_standard_deal_follows_the_rulebook() {
+        let config = GameConfig::default();
+        for (players, deals) in [(3, [10, 6, 6, 0]), (4, [9, 4, 4, 0]), (5, [8, 3, 3, 0])] {
+            for (round, deal) in deals.into_iter().enumerate() {
+                assert_eq!(
+                    config.cards_to_deal(players, round),
+                    deal,
+                    "round {} for {}",
+                    round,
+                    players
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn amounts_that_could_overflow_are_refused() {
+        let standard = GameConfig::default();
+        let refused = [
+            GameConfig {
+                starting_money: Money::MAX,
+                ..standard.clone()
+            },
+            GameConfig {
+                round_values: vec![Money::MAX, 20, 10],
+                ..standard.clone()
+            },
+            GameConfig {
+                deal_schedule: vec![(3, vec![usize::MAX, 1, 1, 0])],
+                ..standard.clone()
+            },
+            GameConfig {
+                distribution: [[usize::MAX; 5]; 5],
+                ..standard.clone()
+            },
+        ];
+        for config in refused {
+            assert!(config.validate(3).is_err(), "{:?}", config);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::fmt;
+
+use super::{card::CardColor, game_state::Money, input::CardID};
+
+// Why an action was refused. Sent to the client as is, so it can point at the
+// offending field instead of showing a bare string.
+#[derive(Debug, Clone, Copy, PartialEq, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+#[archive(check_bytes)]
+pub(crate) enum GameError {
+    NotYourTurn,
+    InvalidAction,
+    NoSuchCard {
+        card: CardID,
+    },
+    NotEnoughMoney {
+        required: Money,
+        available: Money,
+    },
+    WrongCardColor {
+        expected: CardColor,
+        found: CardColor,
+    },
+    DoubleOnDouble,
+    PriceTooLow {
+        highest: Money,
+    },
+    CallTooEarly {
+        wait: f64,
+    },
+    BidsPending,
+    AlreadyHandled,
+    Spectating,
+}
+
+impl GameError {
+    // stable across releases, never reuse a retired code
+    #[cfg(not(feature = "ssr"))]
+    pub(crate) fn code(&self) -> u16 {
+        match self {
+            GameError::NotYourTurn => 1,
+            GameError::InvalidAction => 2,
+            GameError::NoSuchCard { .. } => 3,
+            GameError::NotEnoughMoney { .. } => 4,
+            GameError::WrongCardColor { .. } => 5,
+            GameError::DoubleOnDouble => 6,
+            GameError::PriceTooLow { .. } => 7,
+            GameError::CallTooEarly { .. } => 8,
+            GameError::BidsPending => 9,
+            GameError::AlreadyHandled => 10,
+            GameError::Spectating => 11,
+        }
+    }
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::NotYourTurn => write!(f, "Not your turn yet."),
+            GameError::InvalidAction => write!(f, "Invalid action."),
+            GameError::NoSuchCard { .. } => write!(f, "No such card."),
+            GameError::NotEnoughMoney { required, .. } => {
+                write!(f, "Not enough money, {} needed.", required)
+            }
+            GameError::WrongCardColor { .. } => {
+                write!(
+                    f,
+                    "The card has to be of the same color as the double card."
+                )
+            }
+            GameError::DoubleOnDouble => write!(
+                f,
+                "Cannot set another double card as the target to a previous double card."
+            ),
+            GameError::PriceTooLow { highest } => {
+                write!(
+                    f,
+                    "The current price of {} is higher than your offer.",
+                    highest
+                )
+            }
+            GameError::CallTooEarly { wait } => {
+                write!(f, "Please wait another {:.1} seconds before calling.", wait)
+            }
+            GameError::BidsPending => write!(f, "Somebody has not made their decision yet!"),
+            GameError::AlreadyHandled => write!(f, "This action was already handled."),
+            GameError::Spectating => write!(f, "Spectators cannot play."),
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
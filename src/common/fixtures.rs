@@ -0,0 +1,80 @@
+// Tables for the tests and the fuzzer to play on.
+#[cfg(test)]
+use super::{
+    card::{AuctionType, Card, CardColor},
+    config::GameConfig,
+    game_state::GameState,
+    input::{ActionInput, BidOptionalInner, LegalAction},
+    player::PlayerID,
+};
+#[cfg(test)]
+use rand::{seq::SliceRandom, Rng};
+
+// Vec<(uuid, name)> of `count` made-up players
+pub(crate) fn players(count: usize) -> Vec<(String, String)> {
+    (0..count)
+        .map(|i| (format!("test-{}", i), format!("Player {}", i)))
+        .collect()
+}
+
+// a game of `count` players by the standard rules, dealt from `seed`
+#[cfg(test)]
+pub(crate) fn new_game(count: usize, seed: u64) -> GameState {
+    GameState::new(players(count), GameConfig::default(), seed)
+}
+
+// `count` players with every card back in the pool, to be handed out by a test
+#[cfg(test)]
+pub(crate) fn empty_table(count: usize) -> GameState {
+    let mut state = new_game(count, 0);
+    let hands: Vec<Card> = state
+        .deck
+        .iter_mut()
+        .flat_map(|deck| deck.drain(..))
+        .collect();
+    state.pool.extend(hands);
+    state
+}
+
+// takes a card of this kind out of the pool
+#[cfg(test)]
+pub(crate) fn take(state: &mut GameState, color: CardColor, ty: AuctionType) -> Card {
+    let index = state
+        .pool
+        .iter()
+        .position(|card| card.color == color && card.ty == ty)
+        .unwrap();
+    state.pool.remove(index)
+}
+
+// some legal move of anyone at the table, amounts at the low end
+#[cfg(test)]
+pub(crate) fn random_input(
+    state: &GameState,
+    rng: &mut impl Rng,
+) -> Option<(PlayerID, ActionInput)> {
+    let choices: Vec<_> = (0..state.players.len())
+        .flat_map(|player| {
+            state
+                .legal_actions(player)
+                .into_iter()
+                .map(move |action| (player, action))
+        })
+        .collect();
+    let (player, action) = choices.choose(rng).cloned()?;
+    let input = match action {
+        LegalAction::Input(input) => input,
+        LegalAction::Bid(range) => ActionInput::Bid(*range.start()),
+        LegalAction::BidOptional(range) => {
+            ActionInput::BidOptional(BidOptionalInner::Bid(*range.start()))
+        }
+        LegalAction::AssignMarkedPrice(range) => ActionInput::AssignMarkedPrice(*range.start()),
+    };
+    Some((player, input))
+}
+
+// the states have no PartialEq, their debug output shows every field
+#[cfg(test)]
+pub(crate) fn same(a: &GameState, b: &GameState) -> bool {
+    format!("{:?}", a) == format!("{:?}", b)
+}
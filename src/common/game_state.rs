@@ -1,5 +1,6 @@
 use super::{
-    card::Card,
+    card::{Card, CardColor},
+    config::GameConfig,
     player::{Player, PlayerID},
 };
 
@@ -14,12 +15,21 @@ pub(crate) struct GameState {
     pub(crate) owned_cards: Vec<Vec<Card>>,
     pub(crate) stage: GameStage,
     pub(crate) current_round: usize,
+    pub(crate) round_board: RoundBoard,
     pub(crate) values: [[Money; 5]; 5],
     pub(crate) pool: Vec<Card>,
+    // paintings sold at the end of a round, and cards played to end one
+    pub(crate) discard: Vec<Card>,
+    // what the bank has paid in minus what it took back, masked like the pool
+    pub(crate) issued: Money,
     pub(crate) ended: bool,
+    // bumped by every applied action, deltas are built between versions
+    pub(crate) version: u64,
+    // never changes during a game, so deltas leave it out
+    pub(crate) config: GameConfig,
 }
 
-#[derive(Clone, Debug, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+#[derive(Clone, Debug, PartialEq, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
 #[archive(check_bytes)]
 pub(crate) enum GameStage {
     WaitingForNextCard(PlayerID),
@@ -50,7 +60,7 @@ pub(crate) enum AuctionTarget {
     },
 }
 
-#[derive(Debug, Clone, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+#[derive(Debug, Clone, PartialEq, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
 #[archive(check_bytes)]
 pub(crate) enum AuctionState {
     Free {
@@ -75,33 +85,35 @@ pub(crate) enum AuctionState {
     },
 }
 
-#[derive(Clone, Copy)]
-pub(crate) enum ShouldEnd {
-    RoundEnd,
-    GameEnd,
-    No,
+// Cards played in the current round, per color index. Counted when a card is
+// played rather than when it is sold, so the card that ends a round counts too.
+#[derive(Clone, Debug, PartialEq, Default, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+#[archive(check_bytes)]
+pub(crate) struct RoundBoard {
+    pub(crate) played: [usize; 5],
 }
 
-impl GameState {
-    pub(crate) fn should_end(&self) -> ShouldEnd {
-        let mut counters = vec![0u32; 5];
-        for player in self.owned_cards.iter() {
-            for card in player.iter() {
-                if let Some(count) = counters.get_mut(card.color.index()) {
-                    *count += 1;
-                }
-            }
-        }
+impl RoundBoard {
+    #[cfg(feature = "ssr")]
+    pub(crate) fn play(&mut self, card: &Card) {
+        self.played[card.color.index()] += 1;
+    }
 
-        if counters.iter().find(|count| **count == 5).is_some() {
-            if self.current_round == 3 {
-                ShouldEnd::GameEnd
-            } else {
-                ShouldEnd::RoundEnd
-            }
-        } else {
-            ShouldEnd::No
-        }
+    pub(crate) fn count(&self, color: CardColor) -> usize {
+        self.played[color.index()]
+    }
+
+    #[cfg(feature = "ssr")]
+    // the (up to) `places` colors that score this round, best first;
+    // ties go to the color with the lower index
+    pub(crate) fn rankings(&self, places: usize) -> Vec<CardColor> {
+        let mut colors: Vec<usize> = (0..5).filter(|i| self.played[*i] > 0).collect();
+        colors.sort_by_key(|i| (std::cmp::Reverse(self.played[*i]), *i));
+        colors
+            .into_iter()
+            .take(places)
+            .map(CardColor::from_index)
+            .collect()
     }
 }
 
@@ -120,4 +132,3 @@ impl GameStage {
         }
     }
 }
-
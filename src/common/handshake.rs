@@ -0,0 +1,27 @@
+// The first frame each way on a game socket. They are decoded before anything
+// else, so a stale client can still be told to reload: never change the layout
+// of these two types, bump PROTOCOL_VERSION when GameInput or ServerMessage change.
+
+// bump on any change to the messages sent over the game socket
+pub(crate) const PROTOCOL_VERSION: u32 = 6;
+
+// bit flags of optional features a client understands
+pub(crate) type Capabilities = u32;
+// the client can apply ServerMessage::StateDelta
+pub(crate) const CAP_STATE_DELTA: Capabilities = 1 << 0;
+
+#[derive(Debug, Clone, Copy, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+#[archive(check_bytes)]
+pub(crate) struct Hello {
+    pub(crate) version: u32,
+    pub(crate) capabilities: Capabilities,
+}
+
+#[derive(Debug, Clone, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+#[archive(check_bytes)]
+pub(crate) struct HelloReply {
+    pub(crate) version: u32,
+    pub(crate) accepted: bool,
+    // why the client was turned away, empty when accepted
+    pub(crate) message: String,
+}
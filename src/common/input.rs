@@ -1,19 +1,36 @@
-use super::game_state::Money;
+use super::{game_state::Money, handshake::Capabilities};
+use std::ops::RangeInclusive;
 
 pub(crate) type CardID = usize;
+// numbers a player's actions so answers can refer to them and resends are
+// ignored, inputs the server plays itself carry SERVER_SEQ
+pub(crate) type Seq = u64;
+pub(crate) const SERVER_SEQ: Seq = 0;
+// tells one socket of a seat from the next, unique for the life of the server
+pub(crate) type SessionID = u64;
 
 pub const GAME_WS_URL: &'static str = "/ws/game";
 
 #[derive(Debug, Clone, Copy, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
 #[archive(check_bytes)]
 pub(crate) enum GameInput {
-    Action(ActionInput),
+    Action {
+        seq: Seq,
+        input: ActionInput,
+    },
     RequestState,
-    Connect,
-    Disconnect,
+    // sent by the server once the socket's handshake went through, and when it
+    // closes; a newer session of the same seat replaces the older one
+    Connect {
+        capabilities: Capabilities,
+        session: SessionID,
+    },
+    Disconnect {
+        session: SessionID,
+    },
 }
 
-#[derive(Debug, Clone, Copy, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
 #[archive(check_bytes)]
 pub(crate) enum ActionInput {
     PlayCard(CardID),
@@ -25,24 +42,50 @@ pub(crate) enum ActionInput {
     Call,
 }
 
-#[derive(Debug, Clone, Copy, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
 #[archive(check_bytes)]
 pub(crate) enum MarkedReactionInner {
     Accept,
     Pass,
 }
 
-#[derive(Debug, Clone, Copy, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
 #[archive(check_bytes)]
 pub(crate) enum BidOptionalInner {
     Pass,
     Bid(Money),
 }
 
-#[derive(Debug, Clone, Copy, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
 #[archive(check_bytes)]
 pub(crate) enum PlayCardOptionalInner {
     Pass,
     Play(CardID),
 }
 
+// One kind of move a player may make right now. Inputs carrying an amount of
+// money come with every amount that would be accepted.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum LegalAction {
+    Input(ActionInput),
+    Bid(RangeInclusive<Money>),
+    BidOptional(RangeInclusive<Money>),
+    AssignMarkedPrice(RangeInclusive<Money>),
+}
+
+impl LegalAction {
+    pub(crate) fn allows(&self, input: &ActionInput) -> bool {
+        match (self, input) {
+            (LegalAction::Input(legal), input) => legal == input,
+            (LegalAction::Bid(range), ActionInput::Bid(money))
+            | (
+                LegalAction::BidOptional(range),
+                ActionInput::BidOptional(BidOptionalInner::Bid(money)),
+            )
+            | (LegalAction::AssignMarkedPrice(range), ActionInput::AssignMarkedPrice(money)) => {
+                range.contains(money)
+            }
+            _ => false,
+        }
+    }
+}
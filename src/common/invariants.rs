@@ -0,0 +1,141 @@
+use super::{
+    card::Card,
+    game_state::{AuctionState, AuctionTarget, GameStage, GameState, Money},
+    player::PlayerID,
+};
+
+impl GameState {
+    // Checks what no transition may ever break on the full, unmasked state:
+    // money is neither made nor lost, every card is in exactly one place and
+    // every player ID points at a seat. Returns what broke first.
+    pub(crate) fn check_invariants(&self) -> Result<(), String> {
+        let player_count = self.players.len();
+        for (i, player) in self.players.iter().enumerate() {
+            if player.id != i {
+                return Err(format!("player in seat {} has ID {}", i, player.id));
+            }
+        }
+        let seats = [
+            ("deck", self.deck.len()),
+            ("money", self.money.len()),
+            ("owned_cards", self.owned_cards.len()),
+        ];
+        for (name, len) in seats {
+            if len != player_count {
+                return Err(format!(
+                    "{} has {} seats for {} players",
+                    name, len, player_count
+                ));
+            }
+        }
+        for player in self.stage_players() {
+            if player >= player_count {
+                return Err(format!("stage refers to player {}", player));
+            }
+        }
+        if let GameStage::AuctionInAction {
+            state: AuctionState::Fist {
+                bids, action_taken, ..
+            },
+            ..
+        } = &self.stage
+        {
+            if bids.len() != player_count || action_taken.len() != player_count {
+                return Err("sealed bids do not match the seats".to_string());
+            }
+        }
+
+        let total: Money = self.money.iter().sum();
+        if total != self.issued {
+            return Err(format!(
+                "players hold {} but the bank paid in {}",
+                total, self.issued
+            ));
+        }
+
+        let mut places: Vec<(Card, &str)> = Vec::new();
+        places.extend(self.deck.iter().flatten().map(|card| (*card, "a hand")));
+        places.extend(
+            self.owned_cards
+                .iter()
+                .flatten()
+                .map(|card| (*card, "a collection")),
+        );
+        places.extend(self.pool.iter().map(|card| (*card, "the pool")));
+        places.extend(self.discard.iter().map(|card| (*card, "the discard")));
+        places.extend(
+            self.stage_cards()
+                .into_iter()
+                .map(|card| (card, "the table")),
+        );
+        let cards = self.config.cards();
+        for card in cards.iter() {
+            let found: Vec<&str> = places
+                .iter()
+                .filter(|(other, _)| other == card)
+                .map(|(_, place)| *place)
+                .collect();
+            if found.len() != 1 {
+                return Err(format!("card {} is in {:?}", card.id, found));
+            }
+        }
+        if let Some((card, place)) = places.iter().find(|(card, _)| !cards.contains(card)) {
+            return Err(format!("unknown card {} in {}", card.id, place));
+        }
+        Ok(())
+    }
+
+    // everyone the stage refers to
+    fn stage_players(&self) -> Vec<PlayerID> {
+        let target_players = |target: &AuctionTarget| match target {
+            AuctionTarget::Single((owner, _)) => vec![*owner],
+            AuctionTarget::Double {
+                double_card,
+                target_card,
+            } => vec![double_card.0, target_card.0],
+        };
+        match &self.stage {
+            GameStage::WaitingForNextCard(player) => vec![*player],
+            GameStage::WaitingForDoubleTarget {
+                double_card,
+                current,
+            } => vec![double_card.0, *current],
+            GameStage::WaitingForMarkedPrice { starter, target } => {
+                let mut players = target_players(target);
+                players.push(*starter);
+                players
+            }
+            GameStage::AuctionInAction { state, target } => {
+                let mut players = target_players(target);
+                match state {
+                    AuctionState::Free { host, highest, .. } => players.extend([*host, highest.0]),
+                    AuctionState::Circle {
+                        starter,
+                        current_player,
+                        highest,
+                    } => players.extend([*starter, *current_player, highest.0]),
+                    AuctionState::Fist { host, .. } => players.push(*host),
+                    AuctionState::Marked { price, current } => players.extend([price.0, *current]),
+                }
+                players
+            }
+        }
+    }
+
+    // the cards on the table, played but not sold yet
+    fn stage_cards(&self) -> Vec<Card> {
+        let target_cards = |target: &AuctionTarget| match target {
+            AuctionTarget::Single((_, card)) => vec![*card],
+            AuctionTarget::Double {
+                double_card,
+                target_card,
+            } => vec![double_card.1, target_card.1],
+        };
+        match &self.stage {
+            GameStage::WaitingForNextCard(_) => Vec::new(),
+            GameStage::WaitingForDoubleTarget { double_card, .. } => vec![double_card.1],
+            GameStage::WaitingForMarkedPrice { target, .. }
+            | GameStage::AuctionInAction { target, .. } => target_cards(target),
+        }
+    }
+}
@@ -1,9 +1,18 @@
 pub(crate) mod card;
+pub(crate) mod clock;
+pub mod config;
+pub(crate) mod error;
+#[cfg(all(feature = "ssr", any(test, feature = "fuzz")))]
+pub(crate) mod fixtures;
 pub(crate) mod game_state;
+pub(crate) mod handshake;
+#[cfg(feature = "ssr")]
+pub(crate) mod invariants;
 pub mod input;
 pub(crate) mod placeholder;
 pub(crate) mod player;
+pub(crate) mod rules;
 pub(crate) mod server_message;
+pub(crate) mod state_delta;
 
 // TODO: re-export
-
@@ -1,7 +1,7 @@
 use super::{
     card::Card,
-    game_state::{AuctionTarget, CardPair, Money, MoneyPair},
-    player::PlayerID,
+    game_state::{AuctionTarget, CardPair, GameStage, GameState, Money, MoneyPair},
+    player::{Player, PlayerID},
 };
 
 pub trait PlaceHolder {
@@ -48,3 +48,35 @@ impl PlaceHolder for AuctionTarget {
     }
 }
 
+impl PlaceHolder for Player {
+    fn placeholder() -> Self {
+        Self {
+            uuid: String::new(),
+            id: PlayerID::placeholder(),
+            name: String::new(),
+            connected: false,
+        }
+    }
+}
+
+// shaped like a masked state: a single hand and balance at index 0
+impl PlaceHolder for GameState {
+    fn placeholder() -> Self {
+        Self {
+            deck: vec![Vec::new()],
+            money: vec![Money::placeholder()],
+            players: Vec::new(),
+            owned_cards: Vec::new(),
+            stage: GameStage::WaitingForNextCard(PlayerID::placeholder()),
+            current_round: 0,
+            round_board: Default::default(),
+            values: [[0; 5]; 5],
+            pool: Vec::new(),
+            discard: Vec::new(),
+            issued: 0,
+            ended: false,
+            version: 0,
+            config: Default::default(),
+        }
+    }
+}
@@ -1,8 +1,6 @@
-use super::card::Card;
-
 pub(crate) type PlayerID = usize;
 
-#[derive(Debug, Clone, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+#[derive(Debug, Clone, PartialEq, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
 #[archive(check_bytes)]
 pub(crate) struct Player {
     pub(crate) uuid: String,
@@ -0,0 +1,1237 @@
+// The rules of the game as pure state transitions. Shared with the client so it
+// can check a move before sending it, the server stays the one that applies them.
+use super::{
+    card::AuctionType,
+    error::GameError,
+    game_state::{AuctionState, GameStage, GameState, Money},
+    input::{
+        ActionInput, BidOptionalInner, LegalAction, MarkedReactionInner, PlayCardOptionalInner,
+    },
+    player::PlayerID,
+};
+#[cfg(feature = "ssr")]
+use super::{
+    card::Card, clock::Clock, game_state::AuctionTarget, input::CardID, player::Player,
+    server_message::GameEvent,
+};
+
+#[cfg(feature = "ssr")]
+// What one auction game does differently from another once the cards are on
+// the table. The engine runs the auctions and asks these when a round is over,
+// how it is scored and what is dealt next.
+pub(crate) trait RoundRules {
+    // whether the cards played this round end it
+    fn round_over(&self, state: &GameState) -> bool;
+
+    // whether the game ends with the current round
+    fn is_last_round(&self, state: &GameState) -> bool;
+
+    // pays out the round that just ended and clears its board
+    fn score_round(&self, state: &mut GameState) -> GameEvent;
+
+    // moves on to the next round and deals its cards, if any
+    fn deal_round(&self, state: &mut GameState) -> Option<GameEvent>;
+}
+
+impl GameState {
+    #[cfg(feature = "ssr")]
+    // scores the round that just ended, then deals the next one or ends the game
+    fn finish_round(&mut self, rules: &impl RoundRules, events: &mut Vec<GameEvent>) {
+        if rules.is_last_round(self) {
+            events.push(rules.score_round(self));
+            events.push(GameEvent::GameEnd);
+            self.ended = true;
+        } else {
+            events.push(GameEvent::RoundEnd);
+            events.push(rules.score_round(self));
+            events.extend(rules.deal_round(self));
+        }
+    }
+
+    #[cfg(feature = "ssr")]
+    pub(self) fn get_next_player_rounded(&self, current: PlayerID) -> PlayerID {
+        let mut next = current + 1;
+        if next > self.players.len() - 1 {
+            next = 0;
+        }
+        next
+    }
+
+    #[cfg(feature = "ssr")]
+    pub(self) fn get_next_player(&self, starter: PlayerID, current: PlayerID) -> Option<PlayerID> {
+        let next = self.get_next_player_rounded(current);
+        if next == starter {
+            return None;
+        } else {
+            return Some(next);
+        }
+    }
+
+    #[cfg(feature = "ssr")]
+    fn test_enough_money(&self, player: PlayerID, money: Money) -> Result<(), GameError> {
+        let available = *self.money.get(player).unwrap();
+        if available >= money {
+            Ok(())
+        } else {
+            Err(GameError::NotEnoughMoney {
+                required: money,
+                available,
+            })
+        }
+    }
+
+    #[cfg(feature = "ssr")]
+    fn get_card(&self, player_id: PlayerID, card_id: CardID) -> Result<&Card, GameError> {
+        self.deck
+            .get(player_id)
+            .unwrap()
+            .iter()
+            .filter(|card| card.id == card_id)
+            .next()
+            .ok_or(GameError::NoSuchCard { card: card_id })
+    }
+
+    #[cfg(feature = "ssr")]
+    fn complete_transaction(
+        &mut self,
+        target: AuctionTarget,
+        money: Money,
+        current: PlayerID,
+    ) -> Result<GameStage, GameError> {
+        let available = self.money[current];
+        self.money[current] = available
+            .checked_sub(money)
+            .ok_or(GameError::NotEnoughMoney {
+                required: money,
+                available,
+            })?;
+        let owner;
+        match target {
+            AuctionTarget::Single((starter, card)) => {
+                self.owned_cards[current].push(card);
+                owner = starter;
+            }
+            AuctionTarget::Double {
+                double_card: (_, double_card),
+                target_card: (starter, target_card),
+            } => {
+                self.owned_cards[current].push(double_card);
+                self.owned_cards[current].push(target_card);
+                owner = starter;
+            }
+        }
+        if owner != current {
+            *self.money.get_mut(owner).unwrap() += money;
+        } else {
+            // buying your own painting pays the bank
+            self.issued -= money;
+        }
+        let next = self.get_next_player_rounded(owner);
+        Ok(GameStage::WaitingForNextCard(next))
+    }
+
+    #[cfg(feature = "ssr")]
+    // The first player from `player` on who still has a card to play. Once every
+    // hand is empty the round is over, just as if a color had run out.
+    fn next_turn(
+        &mut self,
+        rules: &impl RoundRules,
+        player: PlayerID,
+        events: &mut Vec<GameEvent>,
+    ) -> GameStage {
+        let player_count = self.players.len();
+        loop {
+            let next = (0..player_count)
+                .map(|offset| (player + offset) % player_count)
+                .find(|next| !self.deck[*next].is_empty());
+            if let Some(next) = next {
+                return GameStage::WaitingForNextCard(next);
+            }
+            self.finish_round(rules, events);
+            if self.ended {
+                return GameStage::WaitingForNextCard(player);
+            }
+        }
+    }
+
+    #[cfg(feature = "ssr")]
+    // applies `input` by the auction rules and `rules` for the rounds, and
+    // returns what happened, in order
+    pub fn process_input(
+        &mut self,
+        rules: &impl RoundRules,
+        from: PlayerID,
+        input: ActionInput,
+        clock: &impl Clock,
+    ) -> Result<Vec<GameEvent>, GameError> {
+        if self.ended {
+            return Err(GameError::InvalidAction);
+        }
+        let mut events = Vec::new();
+        let next_stage = match (&self.stage, input) {
+            (GameStage::WaitingForNextCard(player_id), ActionInput::PlayCard(card_id)) => {
+                if *player_id == from {
+                    let card =
+                        play_card(&mut self.deck, self.players.get_mut(from).unwrap(), card_id)?;
+                    self.round_board.play(&card);
+                    events.push(GameEvent::CardPlayed { player: from, card });
+                    if rules.round_over(self) {
+                        // the card that ends a round is never auctioned
+                        self.discard.push(card);
+                        self.finish_round(rules, &mut events);
+                        let next = self.get_next_player_rounded(from);
+                        GameStage::WaitingForNextCard(next)
+                    } else if let AuctionType::Double = card.ty {
+                        let next = self.get_next_player_rounded(from);
+                        events.push(GameEvent::DoubleTargetOffered { player: next });
+                        GameStage::WaitingForDoubleTarget {
+                            double_card: (from, card),
+                            current: next,
+                        }
+                    } else if let AuctionType::Marked = card.ty {
+                        GameStage::WaitingForMarkedPrice {
+                            starter: from,
+                            target: AuctionTarget::Single((from, card)),
+                        }
+                    } else {
+                        let state = self.gen_auction_state(&card, from, self.players.len(), clock);
+                        GameStage::AuctionInAction {
+                            state,
+                            target: AuctionTarget::Single((from, card)),
+                        }
+                    }
+                } else {
+                    return Err(GameError::NotYourTurn);
+                }
+            }
+            (
+                GameStage::WaitingForDoubleTarget {
+                    double_card,
+                    current,
+                },
+                ActionInput::PlayCardOptional(inner),
+            ) => {
+                if *current == from {
+                    match inner {
+                        PlayCardOptionalInner::Pass => {
+                            events.push(GameEvent::DoubleTargetPassed { player: from });
+                            let owner = double_card.0;
+                            if *current == owner {
+                                // nobody supplied a second card, the owner keeps the double for free
+                                self.owned_cards[owner].push(double_card.1);
+                                events.push(GameEvent::DoubleFreeGet {
+                                    owner,
+                                    card: double_card.1,
+                                });
+                                let next = self.get_next_player_rounded(owner);
+                                GameStage::WaitingForNextCard(next)
+                            } else {
+                                match self.get_next_player(owner, *current) {
+                                    Some(next) => {
+                                        events
+                                            .push(GameEvent::DoubleTargetOffered { player: next });
+                                        GameStage::WaitingForDoubleTarget {
+                                            double_card: *double_card,
+                                            current: next,
+                                        }
+                                    }
+                                    None => {
+                                        // everyone else passed, the owner may still auction it
+                                        events.push(GameEvent::DoubleReturned { owner });
+                                        events
+                                            .push(GameEvent::DoubleTargetOffered { player: owner });
+                                        GameStage::WaitingForDoubleTarget {
+                                            double_card: *double_card,
+                                            current: owner,
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                        PlayCardOptionalInner::Play(card_id) => {
+                            let card = self.get_card(from, card_id)?;
+                            if card.color != double_card.1.color {
+                                return Err(GameError::WrongCardColor {
+                                    expected: double_card.1.color,
+                                    found: card.color,
+                                });
+                            }
+                            if let AuctionType::Double = card.ty {
+                                return Err(GameError::DoubleOnDouble);
+                            }
+                            let card = play_card(
+                                &mut self.deck,
+                                self.players.get_mut(from).unwrap(),
+                                card_id,
+                            )?;
+                            self.round_board.play(&card);
+                            events.push(GameEvent::CardPlayed { player: from, card });
+                            if rules.round_over(self) {
+                                // neither half of the pair is auctioned
+                                self.discard.extend([double_card.1, card]);
+                                self.finish_round(rules, &mut events);
+                                let next = self.get_next_player_rounded(from);
+                                GameStage::WaitingForNextCard(next)
+                            } else {
+                                // whoever supplied the second card runs the auction
+                                events.push(GameEvent::DoubleAuctioneer {
+                                    auctioneer: from,
+                                    double_card: *double_card,
+                                    target_card: (from, card),
+                                });
+                                if let AuctionType::Marked = card.ty {
+                                    GameStage::WaitingForMarkedPrice {
+                                        starter: from,
+                                        target: AuctionTarget::Double {
+                                            double_card: *double_card,
+                                            target_card: (from, card),
+                                        },
+                                    }
+                                } else {
+                                    let state = self.gen_auction_state(
+                                        &card,
+                                        from,
+                                        self.players.len(),
+                                        clock,
+                                    );
+                                    GameStage::AuctionInAction {
+                                        state,
+                                        target: AuctionTarget::Double {
+                                            double_card: *double_card,
+                                            target_card: (from, card),
+                                        },
+                                    }
+                                }
+                            }
+                        }
+                    }
+                } else {
+                    return Err(GameError::NotYourTurn);
+                }
+            }
+            (
+                GameStage::WaitingForMarkedPrice { starter, target },
+                ActionInput::AssignMarkedPrice(money),
+            ) => {
+                if *starter == from {
+                    self.test_enough_money(from, money)?;
+                    events.push(GameEvent::MarkedPriceSet {
+                        starter: from,
+                        price: money,
+                    });
+                    let next = self.get_next_player_rounded(from);
+                    GameStage::AuctionInAction {
+                        state: AuctionState::Marked {
+                            current: next,
+                            price: (from, money),
+                        },
+                        target: *target,
+                    }
+                } else {
+                    return Err(GameError::NotYourTurn);
+                }
+            }
+            (GameStage::AuctionInAction { state, target }, ActionInput::Bid(money)) => {
+                self.test_enough_money(from, money)?;
+                match state {
+                    AuctionState::Free { host, highest, .. } => {
+                        if money > highest.1 {
+                            events.push(GameEvent::BidPlaced {
+                                player: from,
+                                money,
+                            });
+                            let highest = (from, money);
+                            let calls = 0;
+                            GameStage::AuctionInAction {
+                                state: AuctionState::Free {
+                                    host: *host,
+                                    highest,
+                                    time_end: clock.now() + self.config.free_auction_window,
+                                    calls,
+                                },
+                                target: *target,
+                            }
+                        } else {
+                            return Err(GameError::PriceTooLow { highest: highest.1 });
+                        }
+                    }
+                    AuctionState::Fist {
+                        host,
+                        bids,
+                        action_taken,
+                    } => {
+                        events.push(GameEvent::SealedBidPlaced { player: from });
+                        let mut bids = bids.clone();
+                        *bids.get_mut(from).unwrap() = money;
+                        let mut action_taken = action_taken.clone();
+                        *action_taken.get_mut(from).unwrap() = true;
+                        GameStage::AuctionInAction {
+                            state: AuctionState::Fist {
+                                host: *host,
+                                bids,
+                                action_taken,
+                            },
+                            target: *target,
+                        }
+                    }
+                    _ => {
+                        return Err(GameError::InvalidAction);
+                    }
+                }
+            }
+            (GameStage::AuctionInAction { state, target }, ActionInput::BidOptional(inner)) => {
+                match state {
+                    AuctionState::Circle {
+                        starter,
+                        current_player,
+                        highest,
+                    } => {
+                        if *current_player == from {
+                            match inner {
+                                BidOptionalInner::Pass => {
+                                    events.push(GameEvent::BidPassed { player: from });
+                                    if *current_player == *starter {
+                                        events.push(GameEvent::AuctionComplete {
+                                            target: *target,
+                                            buyer: *highest,
+                                            seller: *starter,
+                                        });
+                                        self.complete_transaction(*target, highest.1, highest.0)?
+                                    } else {
+                                        let next = self.get_next_player_rounded(*current_player);
+                                        GameStage::AuctionInAction {
+                                            state: AuctionState::Circle {
+                                                starter: *starter,
+                                                current_player: next,
+                                                highest: *highest,
+                                            },
+                                            target: *target,
+                                        }
+                                    }
+                                }
+                                BidOptionalInner::Bid(money) => {
+                                    self.test_enough_money(from, money)?;
+                                    if money < highest.1 {
+                                        return Err(GameError::PriceTooLow { highest: highest.1 });
+                                    }
+                                    events.push(GameEvent::BidPlaced {
+                                        player: from,
+                                        money,
+                                    });
+                                    let highest = (from, money);
+                                    if *current_player == *starter {
+                                        events.push(GameEvent::AuctionComplete {
+                                            target: *target,
+                                            buyer: highest,
+                                            seller: *starter,
+                                        });
+                                        self.complete_transaction(*target, highest.1, highest.0)?
+                                    } else {
+                                        let next = self.get_next_player_rounded(*current_player);
+                                        GameStage::AuctionInAction {
+                                            state: AuctionState::Circle {
+                                                starter: *starter,
+                                                current_player: next,
+                                                highest,
+                                            },
+                                            target: *target,
+                                        }
+                                    }
+                                }
+                            }
+                        } else {
+                            return Err(GameError::NotYourTurn);
+                        }
+                    }
+                    _ => {
+                        return Err(GameError::InvalidAction);
+                    }
+                }
+            }
+            (GameStage::AuctionInAction { state, target }, ActionInput::MarkedReaction(inner)) => {
+                match state {
+                    AuctionState::Marked { current, price } => {
+                        if *current == from {
+                            if price.0 == *current {
+                                events.push(GameEvent::AuctionComplete {
+                                    target: *target,
+                                    buyer: (*current, price.1),
+                                    seller: price.0,
+                                });
+                                self.complete_transaction(*target, price.1, from)?
+                            } else {
+                                match inner {
+                                    MarkedReactionInner::Accept => {
+                                        self.test_enough_money(from, price.1)?;
+                                        events.push(GameEvent::AuctionComplete {
+                                            target: *target,
+                                            buyer: (*current, price.1),
+                                            seller: price.0,
+                                        });
+                                        self.complete_transaction(*target, price.1, from)?
+                                    }
+                                    MarkedReactionInner::Pass => {
+                                        events.push(GameEvent::BidPassed { player: from });
+                                        let next = self.get_next_player_rounded(*current);
+                                        GameStage::AuctionInAction {
+                                            state: AuctionState::Marked {
+                                                current: next,
+                                                price: *price,
+                                            },
+                                            target: *target,
+                                        }
+                                    }
+                                }
+                            }
+                        } else {
+                            return Err(GameError::NotYourTurn);
+                        }
+                    }
+                    _ => {
+                        return Err(GameError::InvalidAction);
+                    }
+                }
+            }
+            (GameStage::AuctionInAction { state, target }, ActionInput::Call) => match state {
+                AuctionState::Free {
+                    host,
+                    highest,
+                    time_end,
+                    calls,
+                } => {
+                    if *host == from {
+                        if clock.now() > *time_end {
+                            events.push(GameEvent::AuctionCall {
+                                host: *host,
+                                calls: calls + 1,
+                            });
+                            if *calls == 2 {
+                                events.push(GameEvent::AuctionComplete {
+                                    target: *target,
+                                    buyer: *highest,
+                                    seller: *host,
+                                });
+                                self.complete_transaction(*target, highest.1, highest.0)?
+                            } else {
+                                let calls = calls + 1;
+                                GameStage::AuctionInAction {
+                                    state: AuctionState::Free {
+                                        host: *host,
+                                        highest: *highest,
+                                        time_end: clock.now() + self.config.free_auction_window,
+                                        calls,
+                                    },
+                                    target: *target,
+                                }
+                            }
+                        } else {
+                            return Err(GameError::CallTooEarly {
+                                wait: *time_end - clock.now(),
+                            });
+                        }
+                    } else {
+                        return Err(GameError::NotYourTurn);
+                    }
+                }
+                AuctionState::Fist {
+                    host,
+                    bids,
+                    action_taken,
+                } => {
+                    if from != *host {
+                        return Err(GameError::InvalidAction);
+                    }
+                    if action_taken.contains(&false) {
+                        return Err(GameError::BidsPending);
+                    }
+                    let max = bids.iter().max().unwrap();
+                    // ties go to the host, then to whoever sits closest after them
+                    let player_count = self.players.len();
+                    let max_index = (0..player_count)
+                        .map(|offset| (*host + offset) % player_count)
+                        .find(|player| bids[*player] == *max)
+                        .unwrap();
+                    events.push(GameEvent::SealedBidsRevealed {
+                        host: *host,
+                        bids: bids.clone(),
+                    });
+                    events.push(GameEvent::AuctionComplete {
+                        target: *target,
+                        buyer: (max_index, *max),
+                        seller: *host,
+                    });
+                    self.complete_transaction(*target, *max, max_index)?
+                }
+                _ => {
+                    return Err(GameError::InvalidAction);
+                }
+            },
+            _ => {
+                return Err(GameError::InvalidAction);
+            }
+        };
+        let next_stage = match next_stage {
+            GameStage::WaitingForNextCard(next) if !self.ended => {
+                self.next_turn(rules, next, &mut events)
+            }
+            stage => stage,
+        };
+        self.stage = next_stage;
+        self.version += 1;
+        #[cfg(debug_assertions)]
+        if let Err(broken) = self.check_invariants() {
+            panic!(
+                "{:?} from player {} broke the game: {}",
+                input, from, broken
+            );
+        }
+
+        Ok(events)
+    }
+
+    // Every move `player` may make right now, by the rules process_input applies.
+    // Also works on a state masked for `player`. A free auction's host is
+    // offered Call throughout, though it is refused until the window is over.
+    pub(crate) fn legal_actions(&self, player: PlayerID) -> Vec<LegalAction> {
+        let mut actions = Vec::new();
+        if self.ended || player >= self.players.len() {
+            return actions;
+        }
+        let hand = &self.deck[self.seat(player)];
+        let affordable = self.affordable(player);
+        let up_to = |min: Money| affordable.filter(|max| *max >= min).map(|max| min..=max);
+        match &self.stage {
+            GameStage::WaitingForNextCard(current) => {
+                if *current == player {
+                    actions.extend(
+                        hand.iter()
+                            .map(|card| LegalAction::Input(ActionInput::PlayCard(card.id))),
+                    );
+                }
+            }
+            GameStage::WaitingForDoubleTarget {
+                double_card,
+                current,
+            } => {
+                if *current == player {
+                    actions.push(LegalAction::Input(ActionInput::PlayCardOptional(
+                        PlayCardOptionalInner::Pass,
+                    )));
+                    actions.extend(
+                        hand.iter()
+                            .filter(|card| card.color == double_card.1.color)
+                            .filter(|card| card.ty != AuctionType::Double)
+                            .map(|card| {
+                                LegalAction::Input(ActionInput::PlayCardOptional(
+                                    PlayCardOptionalInner::Play(card.id),
+                                ))
+                            }),
+                    );
+                }
+            }
+            GameStage::WaitingForMarkedPrice { starter, .. } => {
+                if *starter == player {
+                    actions.extend(up_to(0).map(LegalAction::AssignMarkedPrice));
+                }
+            }
+            GameStage::AuctionInAction { state, .. } => match state {
+                AuctionState::Free { host, highest, .. } => {
+                    actions.extend(up_to(highest.1 + 1).map(LegalAction::Bid));
+                    if *host == player {
+                        actions.push(LegalAction::Input(ActionInput::Call));
+                    }
+                }
+                AuctionState::Circle {
+                    current_player,
+                    highest,
+                    ..
+                } => {
+                    if *current_player == player {
+                        actions.push(LegalAction::Input(ActionInput::BidOptional(
+                            BidOptionalInner::Pass,
+                        )));
+                        actions.extend(up_to(highest.1).map(LegalAction::BidOptional));
+                    }
+                }
+                AuctionState::Fist {
+                    host, action_taken, ..
+                } => {
+                    // a sealed bid may be changed until the host calls
+                    actions.extend(up_to(0).map(LegalAction::Bid));
+                    if *host == player && !action_taken.contains(&false) {
+                        actions.push(LegalAction::Input(ActionInput::Call));
+                    }
+                }
+                AuctionState::Marked { current, price } => {
+                    if *current == player {
+                        actions.push(LegalAction::Input(ActionInput::MarkedReaction(
+                            MarkedReactionInner::Pass,
+                        )));
+                        // back at the seller either answer buys it at their own price
+                        if price.0 == player || affordable.is_some_and(|max| max >= price.1) {
+                            actions.push(LegalAction::Input(ActionInput::MarkedReaction(
+                                MarkedReactionInner::Accept,
+                            )));
+                        }
+                    }
+                }
+            },
+        }
+        actions
+    }
+
+    // Why process_input would refuse `input` from `player`, without applying it.
+    // Works on a state masked for `player`, so the client can explain a move
+    // before sending it. Timing is left to the server.
+    pub(crate) fn check_input(
+        &self,
+        player: PlayerID,
+        input: &ActionInput,
+    ) -> Result<(), GameError> {
+        if self
+            .legal_actions(player)
+            .iter()
+            .any(|action| action.allows(input))
+        {
+            return Ok(());
+        }
+        let available = self.money[self.seat(player)];
+        let too_much = |money: Money| {
+            self.affordable(player)
+                .map_or(true, |max| money > max)
+                .then_some(GameError::NotEnoughMoney {
+                    required: money,
+                    available,
+                })
+        };
+        // like process_input, the kind of input is looked at before whose turn it is
+        if self.ended || !self.fits(input) {
+            return Err(match (&self.stage, *input) {
+                // a bid is paid for before the auction it goes to
+                (GameStage::AuctionInAction { .. }, ActionInput::Bid(money)) if !self.ended => {
+                    too_much(money).unwrap_or(GameError::InvalidAction)
+                }
+                _ => GameError::InvalidAction,
+            });
+        }
+        if !self.stage.is_player_active(player) {
+            return Err(GameError::NotYourTurn);
+        }
+        let hand = &self.deck[self.seat(player)];
+        Err(match (&self.stage, *input) {
+            (_, ActionInput::PlayCard(card))
+            | (_, ActionInput::PlayCardOptional(PlayCardOptionalInner::Play(card)))
+                if !hand.iter().any(|held| held.id == card) =>
+            {
+                GameError::NoSuchCard { card }
+            }
+            (
+                GameStage::WaitingForDoubleTarget { double_card, .. },
+                ActionInput::PlayCardOptional(PlayCardOptionalInner::Play(card_id)),
+            ) => {
+                let card = hand.iter().find(|card| card.id == card_id).unwrap();
+                if card.color != double_card.1.color {
+                    GameError::WrongCardColor {
+                        expected: double_card.1.color,
+                        found: card.color,
+                    }
+                } else {
+                    GameError::DoubleOnDouble
+                }
+            }
+            (GameStage::WaitingForMarkedPrice { .. }, ActionInput::AssignMarkedPrice(money)) => {
+                too_much(money).unwrap_or(GameError::InvalidAction)
+            }
+            (
+                GameStage::AuctionInAction {
+                    state: AuctionState::Free { highest, .. },
+                    ..
+                },
+                ActionInput::Bid(money),
+            )
+            | (
+                GameStage::AuctionInAction {
+                    state: AuctionState::Circle { highest, .. },
+                    ..
+                },
+                ActionInput::BidOptional(BidOptionalInner::Bid(money)),
+            ) => too_much(money).unwrap_or(GameError::PriceTooLow { highest: highest.1 }),
+            (
+                GameStage::AuctionInAction {
+                    state: AuctionState::Fist { .. },
+                    ..
+                },
+                ActionInput::Bid(money),
+            ) => too_much(money).unwrap_or(GameError::InvalidAction),
+            (
+                GameStage::AuctionInAction {
+                    state: AuctionState::Fist { host, .. },
+                    ..
+                },
+                ActionInput::Call,
+            ) if *host == player => GameError::BidsPending,
+            (
+                GameStage::AuctionInAction {
+                    state: AuctionState::Free { host, .. },
+                    ..
+                },
+                ActionInput::Call,
+            ) if *host != player => GameError::NotYourTurn,
+            (
+                GameStage::AuctionInAction {
+                    state: AuctionState::Marked { price, .. },
+                    ..
+                },
+                ActionInput::MarkedReaction(MarkedReactionInner::Accept),
+            ) => too_much(price.1).unwrap_or(GameError::InvalidAction),
+            _ => GameError::InvalidAction,
+        })
+    }
+
+    // whether process_input has a rule for this kind of input at this stage
+    fn fits(&self, input: &ActionInput) -> bool {
+        match (&self.stage, input) {
+            (GameStage::WaitingForNextCard(_), ActionInput::PlayCard(_))
+            | (GameStage::WaitingForDoubleTarget { .. }, ActionInput::PlayCardOptional(_))
+            | (GameStage::WaitingForMarkedPrice { .. }, ActionInput::AssignMarkedPrice(_)) => true,
+            (GameStage::AuctionInAction { state, .. }, input) => matches!(
+                (state, input),
+                (
+                    AuctionState::Free { .. } | AuctionState::Fist { .. },
+                    ActionInput::Bid(_) | ActionInput::Call
+                ) | (AuctionState::Circle { .. }, ActionInput::BidOptional(_))
+                    | (AuctionState::Marked { .. }, ActionInput::MarkedReaction(_))
+            ),
+            _ => false,
+        }
+    }
+
+    // a masked state only keeps the viewer's hand and balance, at index 0
+    fn seat(&self, player: PlayerID) -> usize {
+        if self.deck.len() == self.players.len() {
+            player
+        } else {
+            0
+        }
+    }
+
+    // the most `player` may offer, as test_enough_money sees it
+    fn affordable(&self, player: PlayerID) -> Option<Money> {
+        self.money.get(self.seat(player)).copied()
+    }
+
+    #[cfg(feature = "ssr")]
+    fn gen_auction_state(
+        &self,
+        card: &Card,
+        from: PlayerID,
+        player_count: usize,
+        clock: &impl Clock,
+    ) -> AuctionState {
+        match card.ty {
+            AuctionType::Free => AuctionState::Free {
+                host: from,
+                highest: (from, 0 as Money),
+                time_end: clock.now() + self.config.free_auction_window,
+                calls: 0,
+            },
+            AuctionType::Circle => AuctionState::Circle {
+                starter: from,
+                current_player: self.get_next_player_rounded(from),
+                highest: (from, 0 as Money),
+            },
+            AuctionType::Fist => AuctionState::Fist {
+                host: from,
+                bids: vec![0 as Money; player_count],
+                action_taken: vec![false; player_count],
+            },
+            _ => unreachable!(),
+        }
+    }
+}
+#[cfg(feature = "ssr")]
+
+fn play_card(
+    deck: &mut Vec<Vec<Card>>,
+    from: &mut Player,
+    card_id: CardID,
+) -> Result<Card, GameError> {
+    let player_deck = deck.get_mut(from.id).unwrap();
+    let index = player_deck
+        .iter()
+        .position(|card| card.id == card_id)
+        .ok_or(GameError::NoSuchCard { card: card_id })?;
+    let card = player_deck.remove(index);
+    Ok(card)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common::{
+            card::CardColor,
+            clock::FixedClock,
+            fixtures::{empty_table, take},
+            game_state::MoneyPair,
+        },
+        server::ruleset::ModernArt,
+    };
+
+    #[test]
+    fn round_end_pays_ranked_colors_summed_over_rounds() {
+        let mut state = empty_table(3);
+        state.current_round = 1;
+        // red already scored first place last round
+        state.values[0][CardColor::Red.index()] = 30;
+        state.round_board.played[CardColor::Red.index()] = 4;
+        state.round_board.played[CardColor::Blue.index()] = 3;
+        state.round_board.played[CardColor::Green.index()] = 2;
+        for (player, color) in [
+            (0, CardColor::Green),
+            (1, CardColor::Red),
+            (1, CardColor::Red),
+            (2, CardColor::Blue),
+            (2, CardColor::Yellow),
+        ] {
+            let card = take(&mut state, color, AuctionType::Free);
+            state.owned_cards[player].push(card);
+        }
+        let card = take(&mut state, CardColor::Red, AuctionType::Circle);
+        state.deck[0].push(card);
+
+        // the fifth red ends the round
+        let events = state
+            .process_input(
+                &ModernArt,
+                0,
+                ActionInput::PlayCard(card.id),
+                &FixedClock(0.0),
+            )
+            .unwrap();
+        let Some(GameEvent::RoundScored {
+            round,
+            ranking,
+            payouts,
+        }) = events
+            .into_iter()
+            .find(|event| matches!(event, GameEvent::RoundScored { .. }))
+        else {
+            panic!("the round was not scored");
+        };
+        assert_eq!(round, 1);
+        assert_eq!(
+            ranking,
+            vec![
+                (CardColor::Red, 60),
+                (CardColor::Blue, 20),
+                (CardColor::Green, 10)
+            ]
+        );
+        // yellow did not place, so it sells for nothing
+        assert_eq!(payouts, vec![10, 120, 20]);
+        assert_eq!(state.money, vec![110, 220, 120]);
+        assert!(state.owned_cards.iter().all(|owned| owned.is_empty()));
+        assert_eq!(state.current_round, 2);
+    }
+
+    // who buys a sealed auction hosted by player 1 with these bids
+    fn fist_buyer(bids: [Money; 3]) -> MoneyPair {
+        let mut state = empty_table(3);
+        let card = take(&mut state, CardColor::Blue, AuctionType::Fist);
+        state.deck[1].push(card);
+        state.stage = GameStage::WaitingForNextCard(1);
+        let clock = FixedClock(0.0);
+        state
+            .process_input(&ModernArt, 1, ActionInput::PlayCard(card.id), &clock)
+            .unwrap();
+        for (player, bid) in bids.into_iter().enumerate() {
+            state
+                .process_input(&ModernArt, player, ActionInput::Bid(bid), &clock)
+                .unwrap();
+        }
+        let events = state
+            .process_input(&ModernArt, 1, ActionInput::Call, &clock)
+            .unwrap();
+        events
+            .into_iter()
+            .find_map(|event| match event {
+                GameEvent::AuctionComplete { buyer, .. } => Some(buyer),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn fist_ties_go_to_the_host_then_clockwise() {
+        assert_eq!(fist_buyer([30, 10, 20]), (0, 30));
+        assert_eq!(fist_buyer([20, 10, 20]), (2, 20));
+        assert_eq!(fist_buyer([20, 20, 20]), (1, 20));
+    }
+
+    // player 1 plays a yellow double and everyone else passes on it
+    fn returned_double() -> (GameState, Card) {
+        let mut state = empty_table(3);
+        // a card in every hand, so the round goes on
+        for player in 0..3 {
+            let card = take(&mut state, CardColor::Blue, AuctionType::Free);
+            state.deck[player].push(card);
+        }
+        let double = take(&mut state, CardColor::Yellow, AuctionType::Double);
+        state.deck[1].push(double);
+        state.stage = GameStage::WaitingForNextCard(1);
+        let clock = FixedClock(0.0);
+        state
+            .process_input(&ModernArt, 1, ActionInput::PlayCard(double.id), &clock)
+            .unwrap();
+        let pass = ActionInput::PlayCardOptional(PlayCardOptionalInner::Pass);
+        let events = state.process_input(&ModernArt, 2, pass, &clock).unwrap();
+        assert!(matches!(
+            events[..],
+            [
+                GameEvent::DoubleTargetPassed { player: 2 },
+                GameEvent::DoubleTargetOffered { player: 0 }
+            ]
+        ));
+        let events = state.process_input(&ModernArt, 0, pass, &clock).unwrap();
+        assert!(matches!(
+            events[..],
+            [
+                GameEvent::DoubleTargetPassed { player: 0 },
+                GameEvent::DoubleReturned { owner: 1 },
+                GameEvent::DoubleTargetOffered { player: 1 }
+            ]
+        ));
+        (state, double)
+    }
+
+    #[test]
+    fn a_double_nobody_pairs_goes_to_its_owner_for_free() {
+        let (mut state, double) = returned_double();
+        let pass = ActionInput::PlayCardOptional(PlayCardOptionalInner::Pass);
+        let events = state
+            .process_input(&ModernArt, 1, pass, &FixedClock(0.0))
+            .unwrap();
+        assert!(matches!(
+            events[..],
+            [
+                GameEvent::DoubleTargetPassed { player: 1 },
+                GameEvent::DoubleFreeGet { owner: 1, card }
+            ] if card == double
+        ));
+        assert_eq!(state.owned_cards, vec![vec![], vec![double], vec![]]);
+        assert_eq!(state.money, vec![100; 3]);
+        assert!(matches!(state.stage, GameStage::WaitingForNextCard(2)));
+    }
+
+    #[test]
+    fn an_owner_pairing_their_returned_double_runs_the_auction() {
+        let (mut state, double) = returned_double();
+        let card = take(&mut state, CardColor::Yellow, AuctionType::Circle);
+        state.deck[1].push(card);
+        let play = ActionInput::PlayCardOptional(PlayCardOptionalInner::Play(card.id));
+        let events = state
+            .process_input(&ModernArt, 1, play, &FixedClock(0.0))
+            .unwrap();
+        assert!(matches!(
+            events[..],
+            [
+                GameEvent::CardPlayed { player: 1, .. },
+                GameEvent::DoubleAuctioneer {
+                    auctioneer: 1,
+                    double_card: (1, first),
+                    target_card: (1, second),
+                }
+            ] if first == double && second == card
+        ));
+        assert!(matches!(
+            state.stage,
+            GameStage::AuctionInAction {
+                state: AuctionState::Circle { starter: 1, .. },
+                target: AuctionTarget::Double { .. },
+            }
+        ));
+    }
+
+    // player 0 has put up a red card of this kind, everyone holds a spare blue one
+    fn auction(ty: AuctionType) -> GameState {
+        let mut state = empty_table(3);
+        for player in 0..3 {
+            let card = take(&mut state, CardColor::Blue, AuctionType::Free);
+            state.deck[player].push(card);
+        }
+        let card = take(&mut state, CardColor::Red, ty);
+        state.deck[0].push(card);
+        // an upper bound below the starting money, the bank never paid the rest
+        state.money[1] = 40;
+        state.issued -= 60;
+        state
+            .process_input(
+                &ModernArt,
+                0,
+                ActionInput::PlayCard(card.id),
+                &FixedClock(0.0),
+            )
+            .unwrap();
+        state
+    }
+
+    // the first ranged move of `player` is accepted at both ends and refused
+    // just outside them, with the error check_input predicts
+    fn assert_bounds_agree(state: &GameState, player: PlayerID) {
+        type Amount = fn(Money) -> ActionInput;
+        let (range, input) = state
+            .legal_actions(player)
+            .into_iter()
+            .find_map(|action| match action {
+                LegalAction::Bid(range) => Some((range, ActionInput::Bid as Amount)),
+                LegalAction::BidOptional(range) => Some((
+                    range,
+                    (|money| ActionInput::BidOptional(BidOptionalInner::Bid(money))) as Amount,
+                )),
+                LegalAction::AssignMarkedPrice(range) => {
+                    Some((range, ActionInput::AssignMarkedPrice as Amount))
+                }
+                LegalAction::Input(_) => None,
+            })
+            .unwrap();
+        let clock = FixedClock(0.0);
+        for money in [*range.start(), *range.end()] {
+            let result = state
+                .clone()
+                .process_input(&ModernArt, player, input(money), &clock);
+            assert!(result.is_ok(), "{:?} refused: {:?}", input(money), result);
+        }
+        let below = range.start().checked_sub(1);
+        for money in below.into_iter().chain([range.end() + 1]) {
+            let refused = state
+                .clone()
+                .process_input(&ModernArt, player, input(money), &clock)
+                .unwrap_err();
+            assert_eq!(state.check_input(player, &input(money)), Err(refused));
+        }
+    }
+
+    #[test]
+    fn legal_bid_ranges_are_what_process_input_accepts() {
+        let clock = FixedClock(0.0);
+        let mut free = auction(AuctionType::Free);
+        assert_bounds_agree(&free, 1);
+        free.process_input(&ModernArt, 2, ActionInput::Bid(10), &clock)
+            .unwrap();
+        assert_bounds_agree(&free, 1);
+
+        let mut circle = auction(AuctionType::Circle);
+        assert_bounds_agree(&circle, 1);
+        let bid = ActionInput::BidOptional(BidOptionalInner::Bid(10));
+        circle.process_input(&ModernArt, 1, bid, &clock).unwrap();
+        assert_bounds_agree(&circle, 2);
+
+        assert_bounds_agree(&auction(AuctionType::Fist), 1);
+        assert_bounds_agree(&auction(AuctionType::Marked), 0);
+
+        // a double is auctioned the way its second card is
+        let mut double = auction(AuctionType::Double);
+        let card = take(&mut double, CardColor::Red, AuctionType::Fist);
+        double.deck[1].push(card);
+        let play = ActionInput::PlayCardOptional(PlayCardOptionalInner::Play(card.id));
+        double.process_input(&ModernArt, 1, play, &clock).unwrap();
+        assert_bounds_agree(&double, 1);
+    }
+
+    #[test]
+    fn check_input_refuses_like_process_input() {
+        let mut double = auction(AuctionType::Double);
+        let blue = double.deck[1][0];
+        let red_double = take(&mut double, CardColor::Red, AuctionType::Double);
+        double.deck[1].push(red_double);
+        let mut marked = auction(AuctionType::Marked);
+        marked
+            .process_input(
+                &ModernArt,
+                0,
+                ActionInput::AssignMarkedPrice(60),
+                &FixedClock(0.0),
+            )
+            .unwrap();
+        let free = auction(AuctionType::Free);
+        let fist = auction(AuctionType::Fist);
+        let play =
+            |card: &Card| ActionInput::PlayCardOptional(PlayCardOptionalInner::Play(card.id));
+        let cases = [
+            (&double, 2, ActionInput::PlayCard(blue.id)),
+            (
+                &double,
+                1,
+                ActionInput::PlayCardOptional(PlayCardOptionalInner::Play(999)),
+            ),
+            (&double, 1, play(&blue)),
+            (&double, 1, play(&red_double)),
+            (&double, 1, ActionInput::Call),
+            (&free, 1, ActionInput::Call),
+            (&free, 2, ActionInput::Bid(0)),
+            (&free, 2, ActionInput::AssignMarkedPrice(0)),
+            (&fist, 0, ActionInput::Call),
+            (&fist, 2, ActionInput::Call),
+            (&marked, 2, ActionInput::Bid(10)),
+            (&marked, 2, ActionInput::Bid(500)),
+            (
+                &marked,
+                2,
+                ActionInput::MarkedReaction(MarkedReactionInner::Pass),
+            ),
+            (
+                &marked,
+                1,
+                ActionInput::MarkedReaction(MarkedReactionInner::Accept),
+            ),
+        ];
+        for (state, player, input) in cases {
+            let refused = state
+                .clone()
+                .process_input(&ModernArt, player, input, &FixedClock(0.0))
+                .unwrap_err();
+            assert_eq!(
+                state.check_input(player, &input),
+                Err(refused),
+                "{:?}",
+                input
+            );
+            // the client only has its own view to go by
+            let view = state.mask(player);
+            assert_eq!(
+                view.check_input(player, &input),
+                Err(refused),
+                "{:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn the_last_round_ending_ends_the_game() {
+        let mut state = empty_table(3);
+        state.current_round = 3;
+        state.round_board.played[CardColor::Green.index()] = 4;
+        let card = take(&mut state, CardColor::Green, AuctionType::Free);
+        state.deck[0].push(card);
+        let other = take(&mut state, CardColor::Blue, AuctionType::Free);
+        state.deck[1].push(other);
+        let clock = FixedClock(0.0);
+
+        let events = state
+            .process_input(&ModernArt, 0, ActionInput::PlayCard(card.id), &clock)
+            .unwrap();
+        assert!(matches!(events.last(), Some(GameEvent::GameEnd)));
+        assert!(state.ended);
+        // cards left in a hand are not played any more
+        assert!(matches!(
+            state.process_input(&ModernArt, 1, ActionInput::PlayCard(other.id), &clock),
+            Err(GameError::InvalidAction)
+        ));
+        assert_eq!(
+            state.check_input(1, &ActionInput::PlayCard(other.id)),
+            Err(GameError::InvalidAction)
+        );
+    }
+}
@@ -1,21 +1,46 @@
 use super::{
-    game_state::{AuctionTarget, GameState, MoneyPair},
+    card::{Card, CardColor},
+    error::GameError,
+    game_state::{AuctionTarget, CardPair, GameState, Money, MoneyPair},
+    input::{ActionInput, Seq},
     player::PlayerID,
+    state_delta::StateDelta,
 };
 
 #[derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Debug, Clone)]
 #[archive(check_bytes)]
-pub enum ServerMessage {
+pub(crate) enum ServerMessage {
     StateUpdate(GameState),
+    // patches the state last sent, see GameState::apply
+    StateDelta(StateDelta),
     GameEvent(GameEvent),
     StringMessage(String),
+    // a frame from the client could not be understood
+    ProtocolError(String),
+    // how many people are watching the game
+    SpectatorCount(usize),
+    // the seat is now played from this socket, new actions are numbered after
+    // `last_seq` and unanswered ones should be sent again
+    Connected {
+        last_seq: Seq,
+    },
+    // the action `seq` was applied, only sent to whoever sent it
+    Ack {
+        seq: Seq,
+    },
+    // the action `seq` was refused, only sent to whoever tried it
+    Rejected {
+        seq: Seq,
+        error: GameError,
+        input: ActionInput,
+    },
     Disconnect,
     GameStop,
 }
 
 #[derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Debug, Clone)]
 #[archive(check_bytes)]
-pub enum GameEvent {
+pub(crate) enum GameEvent {
     PlayerConnect(PlayerID),
     PlayerDisconnect(PlayerID),
     AuctionComplete {
@@ -23,7 +48,91 @@ pub enum GameEvent {
         buyer: MoneyPair,
         seller: PlayerID,
     },
+    CardPlayed {
+        player: PlayerID,
+        card: Card,
+    },
+    // `player` may add a second card to the double on the table
+    DoubleTargetOffered {
+        player: PlayerID,
+    },
+    DoubleTargetPassed {
+        player: PlayerID,
+    },
+    MarkedPriceSet {
+        starter: PlayerID,
+        price: Money,
+    },
+    BidPlaced {
+        player: PlayerID,
+        money: Money,
+    },
+    // the amount stays hidden until the host calls
+    SealedBidPlaced {
+        player: PlayerID,
+    },
+    BidPassed {
+        player: PlayerID,
+    },
+    // `auctioneer` added a second card to the double and auctions both, keeping the money
+    DoubleAuctioneer {
+        auctioneer: PlayerID,
+        double_card: CardPair,
+        target_card: CardPair,
+    },
+    // everyone else passed on the double, it is back to its owner
+    DoubleReturned {
+        owner: PlayerID,
+    },
+    // the owner passed as well and takes the double card for free
+    DoubleFreeGet {
+        owner: PlayerID,
+        card: Card,
+    },
+    // the host called a sealed auction, every bid indexed by player
+    SealedBidsRevealed {
+        host: PlayerID,
+        bids: Vec<Money>,
+    },
     RoundEnd,
+    // the colors ranked this round with what each of their cards now sells for,
+    // and how much every player earned selling their paintings
+    RoundScored {
+        round: usize,
+        ranking: Vec<(CardColor, Money)>,
+        payouts: Vec<Money>,
+    },
+    // every player got `count` new cards for `round`, counting from 0
+    CardsDealt {
+        round: usize,
+        count: usize,
+    },
     GameEnd,
+    // the host of a free auction called, 3 means sold
+    AuctionCall {
+        host: PlayerID,
+        calls: u8,
+    },
+    // the player ran out of time and the server acted for them
+    TurnTimeout {
+        player: PlayerID,
+        action: ActionInput,
+    },
 }
 
+impl GameEvent {
+    // whether the client should pop it up, the rest is only worth a line in a log
+    #[cfg(not(feature = "ssr"))]
+    pub(crate) fn is_notable(&self) -> bool {
+        !matches!(
+            self,
+            GameEvent::CardPlayed { .. }
+                | GameEvent::DoubleTargetOffered { .. }
+                | GameEvent::DoubleTargetPassed { .. }
+                | GameEvent::MarkedPriceSet { .. }
+                | GameEvent::BidPlaced { .. }
+                | GameEvent::SealedBidPlaced { .. }
+                | GameEvent::BidPassed { .. }
+        )
+    }
+}
@@ -0,0 +1,163 @@
+use super::{
+    card::Card,
+    game_state::{GameStage, GameState, Money, RoundBoard},
+    player::{Player, PlayerID},
+};
+
+// The fields that changed between two masked states of the same player.
+#[derive(Clone, Debug, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+#[archive(check_bytes)]
+pub(crate) struct StateDelta {
+    pub(crate) base_version: u64,
+    pub(crate) version: u64,
+    pub(crate) ops: Vec<StateOp>,
+}
+
+#[derive(Clone, Debug, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+#[archive(check_bytes)]
+pub(crate) enum StateOp {
+    // the receiver's own hand and balance, index 0 of a masked state
+    Deck(Vec<Card>),
+    Money(Money),
+    Players(Vec<Player>),
+    OwnedCards { player: PlayerID, cards: Vec<Card> },
+    Stage(GameStage),
+    CurrentRound(usize),
+    RoundBoard(RoundBoard),
+    Values([[Money; 5]; 5]),
+    Discard(Vec<Card>),
+    Ended(bool),
+}
+
+impl GameState {
+    #[cfg(feature = "ssr")]
+    // what turns `self` into `new`, both masked for the same player
+    pub(crate) fn diff(&self, new: &GameState) -> StateDelta {
+        let mut ops = Vec::new();
+        if self.deck != new.deck {
+            ops.push(StateOp::Deck(new.deck[0].clone()));
+        }
+        if self.money != new.money {
+            ops.push(StateOp::Money(new.money[0]));
+        }
+        if self.players != new.players {
+            ops.push(StateOp::Players(new.players.clone()));
+        }
+        for (player, cards) in new.owned_cards.iter().enumerate() {
+            if self.owned_cards.get(player) != Some(cards) {
+                ops.push(StateOp::OwnedCards {
+                    player,
+                    cards: cards.clone(),
+                });
+            }
+        }
+        if self.stage != new.stage {
+            ops.push(StateOp::Stage(new.stage.clone()));
+        }
+        if self.current_round != new.current_round {
+            ops.push(StateOp::CurrentRound(new.current_round));
+        }
+        if self.round_board != new.round_board {
+            ops.push(StateOp::RoundBoard(new.round_board.clone()));
+        }
+        if self.values != new.values {
+            ops.push(StateOp::Values(new.values));
+        }
+        if self.discard != new.discard {
+            ops.push(StateOp::Discard(new.discard.clone()));
+        }
+        if self.ended != new.ended {
+            ops.push(StateOp::Ended(new.ended));
+        }
+        StateDelta {
+            base_version: self.version,
+            version: new.version,
+            ops,
+        }
+    }
+
+    // returns false and leaves the state alone if it is not the delta's base,
+    // the caller should then ask for a full snapshot
+    #[cfg(any(test, not(feature = "ssr")))]
+    pub(crate) fn apply(&mut self, delta: StateDelta) -> bool {
+        if self.version != delta.base_version {
+            return false;
+        }
+        for op in delta.ops {
+            match op {
+                StateOp::Deck(deck) => self.deck = vec![deck],
+                StateOp::Money(money) => self.money = vec![money],
+                StateOp::Players(players) => self.players = players,
+                StateOp::OwnedCards { player, cards } => {
+                    if self.owned_cards.len() <= player {
+                        self.owned_cards.resize(player + 1, Vec::new());
+                    }
+                    self.owned_cards[player] = cards;
+                }
+                StateOp::Stage(stage) => self.stage = stage,
+                StateOp::CurrentRound(round) => self.current_round = round,
+                StateOp::RoundBoard(board) => self.round_board = board,
+                StateOp::Values(values) => self.values = values,
+                StateOp::Discard(discard) => self.discard = discard,
+                StateOp::Ended(ended) => self.ended = ended,
+            }
+        }
+        self.version = delta.version;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        common::{
+            clock::ManualClock,
+            fixtures::{new_game, random_input, same},
+        },
+        server::ruleset::ModernArt,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn applying_a_diff_rebuilds_the_new_state() {
+        let mut state = new_game(4, 0);
+        let mut rng = StdRng::seed_from_u64(0);
+        let clock = ManualClock::new(0.0);
+        for _ in 0..200 {
+            let Some((player, input)) = random_input(&state, &mut rng) else {
+                break;
+            };
+            // every free auction window has passed by the next input
+            clock.advance(5.0);
+            let before = state.clone();
+            if state
+                .process_input(&ModernArt, player, input, &clock)
+                .is_err()
+            {
+                continue;
+            }
+            for seat in 0..4 {
+                let mut old = before.mask(seat);
+                let new = state.mask(seat);
+                assert!(old.apply(old.diff(&new)));
+                assert!(same(&old, &new), "seat {} after {:?}", seat, input);
+            }
+            let mut old = before.mask_observer();
+            let new = state.mask_observer();
+            assert!(old.apply(old.diff(&new)));
+            assert!(same(&old, &new), "spectator after {:?}", input);
+        }
+    }
+
+    #[test]
+    fn a_diff_from_another_base_is_refused() {
+        let state = new_game(3, 0);
+        let mut newer = state.mask(0);
+        newer.version += 1;
+        let mut stale = state.mask(0);
+        let delta = newer.diff(&newer);
+        let untouched = stale.clone();
+        assert!(!stale.apply(delta));
+        assert!(same(&stale, &untouched));
+    }
+}
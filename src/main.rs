@@ -10,6 +10,7 @@ async fn main() {
     use mart::client::app::*;
     use mart::common::input::GAME_WS_URL;
     use mart::fileserv::file_and_error_handler;
+    use mart::server::game::restore_games;
     use mart::server::websocket::game_websocket;
     use std::net::{Ipv4Addr, SocketAddr};
 
@@ -25,6 +26,8 @@ async fn main() {
     let addr = SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 3000);
     let routes = generate_route_list(App);
 
+    restore_games();
+
     let game_ws_url = format!("{}/:game_id/:uuid", GAME_WS_URL);
 
     // build our application with a route
@@ -0,0 +1,292 @@
+use super::{
+    ruleset::Ruleset,
+    websocket::{new_session, Audience},
+};
+use crate::common::{
+    card::{AuctionType, Card, CardColor},
+    error::GameError,
+    game_state::{AuctionState, AuctionTarget, GameStage, GameState, Money},
+    input::{
+        ActionInput, BidOptionalInner, GameInput, MarkedReactionInner, PlayCardOptionalInner, Seq,
+    },
+    player::PlayerID,
+    server_message::{GameEvent, ServerMessage},
+};
+use rand::Rng;
+use std::time::Duration;
+use tokio::{
+    sync::{broadcast, broadcast::error::RecvError, mpsc},
+    time::{sleep_until, Instant},
+};
+
+// bots sit at the table under uuids like this one, so a restored game still
+// knows which seats they take
+pub const BOT_UUID_PREFIX: &str = "bot:";
+
+const BOT_NAMES: [&str; 5] = ["Vermeer", "Hokusai", "Kahlo", "Monet", "Klimt"];
+
+// how long a bot looks at the board before acting, so humans can follow along
+const BOT_THINK_TIME: Duration = Duration::from_millis(800);
+
+// the smallest raise a bot makes in an open auction
+const BID_STEP: Money = 5;
+
+pub fn is_bot(uuid: &str) -> bool {
+    uuid.starts_with(BOT_UUID_PREFIX)
+}
+
+// the first bot name nobody at the table uses yet
+pub fn bot_name(taken: &[String]) -> String {
+    BOT_NAMES
+        .iter()
+        .map(|name| format!("{} (bot)", name))
+        .find(|name| !taken.contains(name))
+        .unwrap_or_else(|| format!("Bot {}", taken.len() + 1))
+}
+
+// A computer player. It only ever sees what its seat would see, and plays
+// through the same channel as a human does.
+pub(crate) trait Bot: Send {
+    // every event the table is told about, in order
+    fn observe(&mut self, _event: &GameEvent) {}
+
+    // `state` is masked for `me`, so the hand and balance are at index 0;
+    // returns what to play next, if anything
+    fn act(&mut self, state: &GameState, me: PlayerID) -> Option<ActionInput>;
+}
+
+// Values paintings by what their color would pay if the round ended now, and
+// spends a share of that, never more than it has.
+pub(crate) struct HeuristicBot {
+    // percentage of a painting's value it is willing to pay
+    greed: Money,
+}
+
+impl HeuristicBot {
+    pub(crate) fn new() -> Self {
+        Self {
+            greed: rand::thread_rng().gen_range(55..=85),
+        }
+    }
+
+    fn budget(&self, state: &GameState, target: &AuctionTarget) -> Money {
+        let value = match target {
+            AuctionTarget::Single((_, card)) => card_value(state, card.color, 0),
+            AuctionTarget::Double {
+                target_card: (_, card),
+                ..
+            } => 2 * card_value(state, card.color, 0),
+        };
+        (value * self.greed / 100).min(state.money[0])
+    }
+
+    fn pick_card(&self, state: &GameState, me: PlayerID) -> Option<Card> {
+        let hand = &state.deck[0];
+        let score = |card: &Card| {
+            let owned = state.owned_cards[me]
+                .iter()
+                .filter(|owned| owned.color == card.color)
+                .count() as Money;
+            let mut score = card_value(state, card.color, 1) * (1 + owned);
+            // a double is only worth much with a second card to go with it
+            let paired = hand
+                .iter()
+                .any(|other| other.id != card.id && other.color == card.color);
+            if card.ty == AuctionType::Double && !paired {
+                score /= 2;
+            }
+            score
+        };
+        hand.iter().max_by_key(|card| score(card)).copied()
+    }
+}
+
+impl Bot for HeuristicBot {
+    fn act(&mut self, state: &GameState, me: PlayerID) -> Option<ActionInput> {
+        if state.ended || !state.stage.is_player_active(me) {
+            return None;
+        }
+        match &state.stage {
+            GameStage::WaitingForNextCard(_) => self
+                .pick_card(state, me)
+                .map(|card| ActionInput::PlayCard(card.id)),
+            GameStage::WaitingForDoubleTarget { double_card, .. } => {
+                // handed back to its owner, passing keeps the double for free
+                let card = state.deck[0]
+                    .iter()
+                    .filter(|card| card.color == double_card.1.color)
+                    .find(|card| card.ty != AuctionType::Double);
+                let inner = match card {
+                    Some(card) if double_card.0 != me => PlayCardOptionalInner::Play(card.id),
+                    _ => PlayCardOptionalInner::Pass,
+                };
+                Some(ActionInput::PlayCardOptional(inner))
+            }
+            GameStage::WaitingForMarkedPrice { target, .. } => {
+                // nobody buying means paying it ourselves, so stay within the balance
+                let price = (self.budget(state, target) * 3 / 2).min(state.money[0]);
+                Some(ActionInput::AssignMarkedPrice(price))
+            }
+            GameStage::AuctionInAction {
+                state: auction,
+                target,
+            } => {
+                let budget = self.budget(state, target);
+                match auction {
+                    AuctionState::Free { host, highest, .. } => {
+                        // the server calls for the host once the window is over
+                        if *host == me || highest.0 == me || highest.1 >= budget {
+                            None
+                        } else {
+                            Some(ActionInput::Bid((highest.1 + BID_STEP).min(budget)))
+                        }
+                    }
+                    AuctionState::Circle {
+                        starter, highest, ..
+                    } => {
+                        let inner = if *starter == me {
+                            // the last word, matching the best offer buys it from the bank
+                            if highest.0 != me && highest.1 <= budget {
+                                BidOptionalInner::Bid(highest.1)
+                            } else {
+                                BidOptionalInner::Pass
+                            }
+                        } else if highest.1 < budget {
+                            // one chance per round, so go close to the limit
+                            BidOptionalInner::Bid((highest.1 + 1).max(budget * 3 / 4))
+                        } else {
+                            BidOptionalInner::Pass
+                        };
+                        Some(ActionInput::BidOptional(inner))
+                    }
+                    AuctionState::Fist {
+                        host, action_taken, ..
+                    } => {
+                        if !action_taken[me] {
+                            Some(ActionInput::Bid(budget * 4 / 5))
+                        } else if *host == me && !action_taken.contains(&false) {
+                            Some(ActionInput::Call)
+                        } else {
+                            None
+                        }
+                    }
+                    AuctionState::Marked { price, .. } => {
+                        // coming back round to the seller means they have to buy
+                        let inner = if price.0 == me || price.1 <= budget {
+                            MarkedReactionInner::Accept
+                        } else {
+                            MarkedReactionInner::Pass
+                        };
+                        Some(ActionInput::MarkedReaction(inner))
+                    }
+                }
+            }
+        }
+    }
+}
+
+// What one painting of `color` pays out if the round ended with `extra` more
+// of them played: its value from earlier rounds plus this round's, but only if
+// the color makes the ranking.
+fn card_value(state: &GameState, color: CardColor, extra: usize) -> Money {
+    let mut board = state.round_board.clone();
+    board.played[color.index()] += extra;
+    let values = &state.config.round_values;
+    let Some(rank) = board
+        .rankings(values.len())
+        .iter()
+        .position(|ranked| *ranked == color)
+    else {
+        return 0;
+    };
+    let earlier: Money = (0..state.current_round)
+        .map(|round| state.values[round][color.index()])
+        .sum();
+    earlier + values[rank]
+}
+
+// Seats `bot` at the table as `player_id`. It listens to the game like a socket
+// would and sends its moves into the game actor, numbered after `last_seq`.
+pub(crate) fn spawn_bot<R: Ruleset>(
+    ruleset: R,
+    player_id: PlayerID,
+    mut bot: Box<dyn Bot>,
+    mut read: broadcast::Receiver<(Audience, ServerMessage)>,
+    write: mpsc::UnboundedSender<(PlayerID, GameInput)>,
+    last_seq: Seq,
+) {
+    tokio::spawn(async move {
+        let mut seq = last_seq;
+        let session = new_session();
+        // no capabilities, a bot always gets the whole state
+        let connect = GameInput::Connect {
+            capabilities: 0,
+            session,
+        };
+        if write.send((player_id, connect)).is_err() {
+            return;
+        }
+        let _ = write.send((player_id, GameInput::RequestState));
+
+        let mut state: Option<GameState> = None;
+        // when to look at the board again
+        let mut due: Option<Instant> = None;
+        loop {
+            tokio::select! {
+                received = read.recv() => {
+                    let (audience, message) = match received {
+                        Ok(received) => received,
+                        Err(RecvError::Lagged(_)) => {
+                            let _ = write.send((player_id, GameInput::RequestState));
+                            continue;
+                        }
+                        Err(RecvError::Closed) => break,
+                    };
+                    if !audience.includes(Some(player_id), session) {
+                        continue;
+                    }
+                    match message {
+                        // nothing left to play once the game is over
+                        ServerMessage::StateUpdate(new) if new.ended => break,
+                        ServerMessage::StateUpdate(new) => {
+                            state = Some(new);
+                            due = Some(Instant::now() + BOT_THINK_TIME);
+                        }
+                        ServerMessage::GameEvent(GameEvent::GameEnd) => break,
+                        ServerMessage::GameEvent(event) => bot.observe(&event),
+                        ServerMessage::Rejected {
+                            error: GameError::CallTooEarly { wait },
+                            ..
+                        } => {
+                            due = Some(Instant::now() + Duration::from_secs_f64(wait.max(0.0)));
+                        }
+                        ServerMessage::Rejected { error, input, .. } => {
+                            log::debug!("bot {} had {:?} rejected: {}", player_id, input, error);
+                        }
+                        ServerMessage::Disconnect | ServerMessage::GameStop => break,
+                        _ => {}
+                    }
+                }
+                _ = sleep_until(due.unwrap_or_else(Instant::now)), if due.is_some() => {
+                    due = None;
+                    let Some(state) = state.as_ref() else {
+                        continue;
+                    };
+                    let Some(input) = bot.act(state, player_id) else {
+                        continue;
+                    };
+                    // never bother the table with a move the rules would refuse
+                    let legal = ruleset.legal_actions(state, player_id);
+                    if !legal.iter().any(|action| action.allows(&input)) {
+                        log::debug!("bot {} chose {:?}, which is not legal", player_id, input);
+                        continue;
+                    }
+                    seq += 1;
+                    if write.send((player_id, GameInput::Action { seq, input })).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,154 @@
+use super::game_log::{GameLog, LogEntry};
+use crate::common::game_state::GameState;
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use rkyv::{from_bytes, to_bytes, AlignedVec};
+
+// override with MART_DB_PATH when deploying
+const DEFAULT_DB_PATH: &str = "mart.db";
+const GAMES_TREE: &str = "games";
+const ENTRIES_TREE: &str = "game_entries";
+const ROOMS_TREE: &str = "rooms";
+
+// a running game, the state is always the log replayed to its end
+#[derive(Clone, Debug)]
+pub(crate) struct SavedGame {
+    pub(crate) state: GameState,
+    pub(crate) log: GameLog,
+}
+
+// Written when a game starts and again when it ends. The log is stored without
+// its entries, those are appended one by one as they happen.
+#[derive(Clone, Debug, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+#[archive(check_bytes)]
+struct GameRecord {
+    log: GameLog,
+    ended: bool,
+}
+
+// what a restored game needs to put its room back, the players come from the log
+#[derive(Clone, Debug, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+#[archive(check_bytes)]
+pub(crate) struct RoomRecord {
+    pub(crate) name: String,
+    pub(crate) host: usize,
+}
+
+pub static DATABASE: Lazy<sled::Db> = Lazy::new(|| {
+    // tests write to a throwaway database
+    if cfg!(test) {
+        return sled::Config::new()
+            .temporary(true)
+            .open()
+            .expect("couldn't open a temporary database");
+    }
+    let path = std::env::var("MART_DB_PATH").unwrap_or_else(|_| DEFAULT_DB_PATH.to_string());
+    sled::open(path).expect("couldn't open game database")
+});
+
+fn tree(name: &str) -> Result<sled::Tree> {
+    DATABASE
+        .open_tree(name)
+        .with_context(|| format!("Failed to open tree {}.", name))
+}
+
+// entries of one game sort by index under a common prefix
+fn entries_prefix(game_id: &str) -> Vec<u8> {
+    let mut key = game_id.as_bytes().to_vec();
+    key.push(b'/');
+    key
+}
+
+// sled gives no alignment guarantees, which rkyv validation requires
+fn aligned(value: &[u8]) -> AlignedVec {
+    let mut data = AlignedVec::with_capacity(value.len());
+    data.extend_from_slice(value);
+    data
+}
+
+pub(crate) fn save_record(game_id: &str, log: &GameLog, ended: bool) -> Result<()> {
+    let record = GameRecord {
+        log: log.header(),
+        ended,
+    };
+    let data = to_bytes::<_, 1024>(&record).context("Failed to serialize game.")?;
+    tree(GAMES_TREE)?.insert(game_id, data.as_slice())?;
+    Ok(())
+}
+
+// sled flushes on its own every so often, so a crash loses at most the last
+// moment of play and the game actor never waits on the disk
+pub(crate) fn append_entry(game_id: &str, index: usize, entry: &LogEntry) -> Result<()> {
+    let mut key = entries_prefix(game_id);
+    key.extend_from_slice(&(index as u64).to_be_bytes());
+    let data = to_bytes::<_, 256>(entry).context("Failed to serialize log entry.")?;
+    tree(ENTRIES_TREE)?.insert(key, data.as_slice())?;
+    Ok(())
+}
+
+fn load_record(game_id: &str, value: &[u8]) -> Result<GameRecord> {
+    from_bytes::<GameRecord>(&aligned(value))
+        .map_err(|_| anyhow::anyhow!("Corrupted record of game {}.", game_id))
+}
+
+fn load_entries(game_id: &str, log: &mut GameLog) -> Result<()> {
+    for entry in tree(ENTRIES_TREE)?.scan_prefix(entries_prefix(game_id)) {
+        let (_, value) = entry?;
+        let entry = from_bytes::<LogEntry>(&aligned(&value))
+            .map_err(|_| anyhow::anyhow!("Corrupted log entry of game {}.", game_id))?;
+        log.entries.push(entry);
+    }
+    Ok(())
+}
+
+// the whole log of a game and whether it has ended
+pub(crate) fn load_game(game_id: &str) -> Result<Option<(GameLog, bool)>> {
+    let Some(value) = tree(GAMES_TREE)?.get(game_id)? else {
+        return Ok(None);
+    };
+    let mut record = load_record(game_id, &value)?;
+    load_entries(game_id, &mut record.log)?;
+    Ok(Some((record.log, record.ended)))
+}
+
+// Vec<(game_id, log)>
+pub(crate) fn load_unfinished_games() -> Result<Vec<(String, GameLog)>> {
+    let mut result = Vec::new();
+    for entry in tree(GAMES_TREE)?.iter() {
+        let (key, value) = entry?;
+        let game_id = String::from_utf8(key.to_vec()).context("Invalid game ID.")?;
+        let loaded = load_record(&game_id, &value).and_then(|mut record| {
+            if !record.ended {
+                load_entries(&game_id, &mut record.log)?;
+            }
+            Ok(record)
+        });
+        match loaded {
+            Ok(record) if !record.ended => result.push((game_id, record.log)),
+            Ok(_) => {}
+            Err(error) => log::warn!("skipping saved game: {}", error),
+        }
+    }
+    Ok(result)
+}
+
+pub(crate) fn save_room(room_id: &str, room: &RoomRecord) -> Result<()> {
+    let data = to_bytes::<_, 64>(room).context("Failed to serialize room.")?;
+    tree(ROOMS_TREE)?.insert(room_id, data.as_slice())?;
+    Ok(())
+}
+
+pub(crate) fn load_room(room_id: &str) -> Result<Option<RoomRecord>> {
+    tree(ROOMS_TREE)?
+        .get(room_id)?
+        .map(|value| {
+            from_bytes::<RoomRecord>(&aligned(&value))
+                .map_err(|_| anyhow::anyhow!("Corrupted record of room {}.", room_id))
+        })
+        .transpose()
+}
+
+pub(crate) fn remove_room(room_id: &str) -> Result<()> {
+    tree(ROOMS_TREE)?.remove(room_id)?;
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use super::ruleset::{ModernArt, Ruleset};
+use crate::common::{
+    clock::ManualClock,
+    config::{presets, MAX_PLAYERS, MIN_PLAYERS},
+    error::GameError,
+    fixtures,
+    game_state::Money,
+    input::{ActionInput, BidOptionalInner, LegalAction},
+    player::PlayerID,
+};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use std::ops::RangeInclusive;
+
+// a game that has not ended after this many inputs is stuck in a loop
+const MAX_STEPS: usize = 10_000;
+
+// Plays `games` whole games of Modern Art from random legal inputs, checking the
+// invariants after every one. The same seed always plays the same games. Returns
+// how many inputs were played, or what went wrong and how to get there again.
+pub fn fuzz_games(games: usize, seed: u64) -> Result<usize, String> {
+    fuzz_ruleset(&ModernArt, games, seed)
+}
+
+fn fuzz_ruleset<R: Ruleset>(ruleset: &R, games: usize, seed: u64) -> Result<usize, String> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut played = 0;
+    for game in 0..games {
+        let player_count = rng.gen_range(MIN_PLAYERS..=MAX_PLAYERS);
+        let players = fixtures::players(player_count);
+        // every preset, so house rules get the same coverage
+        let (_, config) = presets().choose(&mut rng).cloned().unwrap();
+        let mut state = ruleset.setup(players, config, rng.gen());
+        let clock = ManualClock::new(0.0);
+        let fail = |step: usize, what: String| {
+            format!("seed {}, game {}, step {}: {}", seed, game, step, what)
+        };
+        state.check_invariants().map_err(|broken| fail(0, broken))?;
+
+        for step in 1..=MAX_STEPS {
+            // everyone who may act, with each kind of move they may make
+            let choices: Vec<(PlayerID, LegalAction)> = (0..player_count)
+                .flat_map(|player| {
+                    ruleset
+                        .legal_actions(&state, player)
+                        .into_iter()
+                        .map(move |action| (player, action))
+                })
+                .collect();
+            let Some((player, action)) = choices.choose(&mut rng).cloned() else {
+                return Err(fail(step, format!("nobody can move in {:?}", state.stage)));
+            };
+            let input = pick_input(action, &mut rng);
+            clock.advance(rng.gen_range(0.0..2.0));
+            let before = state.clone();
+            match ruleset.process_input(&mut state, player, input, &clock) {
+                Ok(_) => {}
+                // legal, just not yet
+                Err(GameError::CallTooEarly { .. }) => {}
+                Err(error) => {
+                    return Err(fail(
+                        step,
+                        format!(
+                            "legal {:?} from player {} refused: {}",
+                            input, player, error
+                        ),
+                    ))
+                }
+            }
+            if let Err(broken) = state.check_invariants() {
+                return Err(fail(
+                    step,
+                    format!(
+                        "{:?} from player {} in {:?}: {}",
+                        input, player, before.stage, broken
+                    ),
+                ));
+            }
+            played += 1;
+            if ruleset.is_over(&state) {
+                break;
+            }
+        }
+        if !ruleset.is_over(&state) {
+            return Err(fail(MAX_STEPS, "the game never ended".to_string()));
+        }
+    }
+    Ok(played)
+}
+
+// one concrete input out of a legal move, amounts lean towards the low end
+// like real bids do but still reach the edges
+fn pick_input(action: LegalAction, rng: &mut impl Rng) -> ActionInput {
+    let amount = |range: RangeInclusive<Money>, rng: &mut dyn rand::RngCore| {
+        let (min, max) = (*range.start(), *range.end());
+        match rng.gen_range(0..10) {
+            0 => min,
+            1 => max,
+            _ => rng.gen_range(min..=min + (max - min) / 4),
+        }
+    };
+    match action {
+        LegalAction::Input(input) => input,
+        LegalAction::Bid(range) => ActionInput::Bid(amount(range, rng)),
+        LegalAction::BidOptional(range) => {
+            ActionInput::BidOptional(BidOptionalInner::Bid(amount(range, rng)))
+        }
+        LegalAction::AssignMarkedPrice(range) => ActionInput::AssignMarkedPrice(amount(range, rng)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fuzz_games;
+
+    // a fixed seed keeps the run reproducible, `cargo run --example fuzz`
+    // covers more ground
+    #[test]
+    fn seeded_games_keep_invariants() {
+        if let Err(error) = fuzz_games(20, 1) {
+            panic!("{}", error);
+        }
+    }
+}
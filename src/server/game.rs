@@ -1,108 +1,558 @@
-use super::websocket::{GameInfo, GAME_INFO_STORE};
+use super::{
+    bot::{is_bot, spawn_bot, HeuristicBot},
+    clock::{Clock, FixedClock, SystemClock},
+    database::{append_entry, load_unfinished_games, save_record, SavedGame, DATABASE},
+    game_log::GameLog,
+    room::{restore_room, set_room_state, RoomState},
+    ruleset::{find_ruleset, Ruleset},
+    timer::{humans_connected, TURN_TIMEOUT},
+    websocket::{Audience, GameInfo, SpectatorInput, GAME_INFO_STORE},
+};
 use crate::common::{
+    config::GameConfig,
+    error::GameError,
     game_state::GameState,
-    input::GameInput,
+    handshake::{Capabilities, CAP_STATE_DELTA},
+    input::{GameInput, SessionID, SERVER_SEQ},
+    player::PlayerID,
     server_message::{GameEvent, ServerMessage},
 };
+use std::time::Duration;
 use tokio::sync::{broadcast, mpsc};
 
-pub fn start_game(players: Vec<(String, String)>, game_id: String, game_state: Option<GameState>) {
+type GameSender = broadcast::Sender<(Audience, ServerMessage)>;
+// what the actor knows about each player's current socket
+struct Sessions {
+    // the masked state sent last, deltas are taken against it
+    sent: Vec<Option<GameState>>,
+    capabilities: Vec<Capabilities>,
+    // the socket each seat plays from, a newer one replaces it
+    current: Vec<Option<SessionID>>,
+    spectators: usize,
+}
+
+impl Sessions {
+    fn new(player_count: usize) -> Self {
+        Self {
+            sent: vec![None; player_count],
+            capabilities: vec![0; player_count],
+            current: vec![None; player_count],
+            spectators: 0,
+        }
+    }
+}
+
+// What the actor holds for one game, every input it handles goes through here.
+// The clock is the one the rules and the log see.
+struct GameContext<R: Ruleset, C: Clock> {
+    ruleset: R,
+    game_id: String,
+    game: SavedGame,
+    sessions: Sessions,
+    write: GameSender,
+    clock: C,
+}
+
+// timers fire this much after a deadline, so the rules agree it has passed
+const TIMER_SLACK: f64 = 0.05;
+
+// `config` and `seed` only apply to a new game, a saved one keeps what it started with
+pub(crate) fn start_game<R: Ruleset>(
+    ruleset: R,
+    players: Vec<(String, String)>,
+    config: GameConfig,
+    seed: u64,
+    game_id: String,
+    saved: Option<SavedGame>,
+) {
     force_stop_game(&game_id);
-    let mut game_state = if let Some(existing) = game_state {
-        existing
-    } else {
-        GameState::new(players.clone())
-    };
+    let game = saved.unwrap_or_else(|| {
+        log::info!("game {} of {} dealt from seed {}", game_id, R::NAME, seed);
+        let log = GameLog::new(R::NAME, players.clone(), config.clone(), seed);
+        if let Err(error) = save_record(&game_id, &log, false) {
+            log::error!("failed to save game {}: {}", game_id, error);
+        }
+        SavedGame {
+            state: ruleset.setup(players.clone(), config, seed),
+            log,
+        }
+    });
 
     let (mpsc_sender, mut read) = mpsc::unbounded_channel();
-    let (write, _) = broadcast::channel(5);
+    let (spectate, mut spectators) = mpsc::unbounded_channel();
+    // every accepted action fans out one masked state per player, plus events
+    let (write, _) = broadcast::channel(64);
 
     let write_clone = write.clone();
 
+    // bots play through the same channel as everyone else
+    for (player_id, (uuid, _)) in players.iter().enumerate() {
+        if is_bot(uuid) {
+            spawn_bot(
+                ruleset.clone(),
+                player_id,
+                Box::new(HeuristicBot::new()),
+                write.subscribe(),
+                mpsc_sender.clone(),
+                game.log.last_seq(player_id),
+            );
+        }
+    }
+
     GAME_INFO_STORE.insert(
-        game_id,
+        game_id.clone(),
         GameInfo {
+            ruleset: R::NAME,
             players,
             read: write_clone,
             write: mpsc_sender,
+            spectate,
         },
     );
 
-    let read_task = tokio::spawn(async move {
-        while let Some((player_id, input)) = read.recv().await {
-            match input {
-                GameInput::RequestState => {
-                    write
-                        .send((
-                            Some(player_id),
-                            ServerMessage::StateUpdate(game_state.mask(player_id)),
-                        ))
-                        .unwrap();
-                }
-                GameInput::Connect => {
-                    if game_state.players[player_id].connected {
-                        write
-                            .send((Some(player_id), ServerMessage::Disconnect))
-                            .unwrap();
-                        game_state.players[player_id].connected = false;
-                        write
-                            .send((
-                                None,
-                                ServerMessage::GameEvent(GameEvent::PlayerDisconnect(player_id)),
-                            ))
-                            .unwrap();
-                    } else {
-                        game_state.players[player_id].connected = true;
-                        write
-                            .send((
-                                None,
-                                ServerMessage::GameEvent(GameEvent::PlayerConnect(player_id)),
-                            ))
-                            .unwrap();
+    tokio::spawn(async move {
+        let mut ctx = GameContext {
+            sessions: Sessions::new(game.state.players.len()),
+            ruleset,
+            game_id,
+            game,
+            write,
+            clock: SystemClock,
+        };
+        let next_deadline = |ctx: &GameContext<R, SystemClock>| {
+            ctx.ruleset
+                .deadline(&ctx.game.state, ctx.clock.now(), *TURN_TIMEOUT)
+        };
+        let mut deadline = next_deadline(&ctx);
+        loop {
+            let sleep = deadline.map(|deadline| {
+                let remaining = (deadline - ctx.clock.now()).max(0.0) + TIMER_SLACK;
+                tokio::time::sleep(Duration::from_secs_f64(remaining))
+            });
+            tokio::select! {
+                received = read.recv() => {
+                    let Some((player_id, input)) = received else {
+                        break;
+                    };
+                    let (accepted, game_end) = handle_input(&mut ctx, player_id, input, None);
+                    if game_end {
+                        return;
                     }
-                }
-                GameInput::Disconnect => {
-                    game_state.players[player_id].connected = false;
-                    write
-                        .send((
-                            None,
-                            ServerMessage::GameEvent(GameEvent::PlayerDisconnect(player_id)),
-                        ))
-                        .unwrap();
-                }
-                GameInput::Action(input) => match game_state.process_input(player_id, input) {
-                    Ok(inner) => {
-                        if let Some(event) = inner {
-                            if let GameEvent::GameEnd = &event {
-                                write.send((None, ServerMessage::GameEvent(event))).unwrap();
-                                game_state.ended = true;
-                                return;
-                                // TODO: write state to store
-                            } else {
-                                write.send((None, ServerMessage::GameEvent(event))).unwrap();
+                    match input {
+                        GameInput::Action { .. } if accepted => deadline = next_deadline(&ctx),
+                        GameInput::Connect { .. } | GameInput::Disconnect { .. } => {
+                            // pause when the table empties, resume when someone is back
+                            if humans_connected(&ctx.game.state) != deadline.is_some() {
+                                deadline = next_deadline(&ctx);
                             }
                         }
-                        write
-                            .send((None, ServerMessage::StateUpdate(game_state.mask(player_id))))
-                            .unwrap();
+                        _ => {}
                     }
-                    Err(message) => {
-                        write
-                            .send((
-                                Some(player_id),
-                                ServerMessage::StringMessage(message.to_string()),
-                            ))
-                            .unwrap();
+                }
+                Some(input) = spectators.recv() => handle_spectator(&mut ctx, input),
+                _ = async { sleep.unwrap().await }, if sleep.is_some() => {
+                    let actions = ctx.ruleset.timeout_actions(&ctx.game.state);
+                    for (player_id, action, reason) in actions {
+                        let input = GameInput::Action {
+                            seq: SERVER_SEQ,
+                            input: action,
+                        };
+                        let (_, game_end) = handle_input(&mut ctx, player_id, input, reason);
+                        if game_end {
+                            return;
+                        }
                     }
-                },
+                    deadline = next_deadline(&ctx);
+                }
             }
         }
     });
 }
 
+// Applies one input, logs it and tells the table about it. `reason` is announced
+// first when the server plays the input on someone's behalf.
+// Client actions are answered with an ack or a rejection, resends of one already
+// handled are answered again but not applied.
+// Returns whether the input was accepted and whether the game is over.
+fn handle_input<R: Ruleset, C: Clock>(
+    ctx: &mut GameContext<R, C>,
+    player_id: PlayerID,
+    input: GameInput,
+    reason: Option<GameEvent>,
+) -> (bool, bool) {
+    let GameContext {
+        ruleset,
+        game_id,
+        game,
+        sessions,
+        write,
+        clock,
+    } = ctx;
+    // read the clock once so the log records exactly what the rules saw
+    let time = clock.now();
+    let by_server = reason.is_some();
+    let mut events = Vec::new();
+    let accepted = match input {
+        GameInput::RequestState => {
+            let state = ruleset.mask(&game.state, Some(player_id));
+            let _ = write.send((
+                Audience::Player(player_id),
+                ServerMessage::StateUpdate(state.clone()),
+            ));
+            let _ = write.send((
+                Audience::Player(player_id),
+                ServerMessage::SpectatorCount(sessions.spectators),
+            ));
+            sessions.sent[player_id] = Some(state);
+            true
+        }
+        GameInput::Connect {
+            capabilities,
+            session,
+        } => {
+            // a new socket starts from a snapshot
+            sessions.sent[player_id] = None;
+            sessions.capabilities[player_id] = capabilities;
+            // the seat moves to the new socket, even if the old one never said goodbye
+            if let Some(old) = sessions.current[player_id].replace(session) {
+                let _ = write.send((Audience::Session(old), ServerMessage::Disconnect));
+            }
+            let last_seq = game.log.last_seq(player_id);
+            let _ = write.send((
+                Audience::Session(session),
+                ServerMessage::Connected { last_seq },
+            ));
+            if game.state.connect(player_id) {
+                events.push(GameEvent::PlayerConnect(player_id));
+            }
+            true
+        }
+        GameInput::Disconnect { session } => {
+            // a replaced socket closing does not take the seat with it
+            if sessions.current[player_id] != Some(session) {
+                return (false, false);
+            }
+            sessions.current[player_id] = None;
+            sessions.sent[player_id] = None;
+            game.state.disconnect(player_id);
+            events.push(GameEvent::PlayerDisconnect(player_id));
+            true
+        }
+        GameInput::Action { seq, input: action } => {
+            if !by_server && seq <= game.log.last_seq(player_id) {
+                let reply = if game.log.was_accepted(player_id, seq) {
+                    ServerMessage::Ack { seq }
+                } else {
+                    ServerMessage::Rejected {
+                        seq,
+                        error: GameError::AlreadyHandled,
+                        input: action,
+                    }
+                };
+                let _ = write.send((Audience::Player(player_id), reply));
+                return (false, false);
+            }
+            match ruleset.process_input(&mut game.state, player_id, action, &FixedClock(time)) {
+                Ok(inner) => {
+                    events.extend(reason);
+                    events.extend(inner);
+                    true
+                }
+                Err(error) => {
+                    if by_server {
+                        log::warn!("server action failed in game {}: {}", game_id, error);
+                    } else {
+                        let _ = write.send((
+                            Audience::Player(player_id),
+                            ServerMessage::Rejected {
+                                seq,
+                                error,
+                                input: action,
+                            },
+                        ));
+                    }
+                    false
+                }
+            }
+        }
+    };
+    game.log
+        .push(time, player_id, input, accepted, events.clone());
+    store_last_entry(game_id, &game.log);
+
+    let game_end = ruleset.is_over(&game.state);
+    for event in events {
+        let _ = write.send((Audience::Everyone, ServerMessage::GameEvent(event)));
+    }
+    let GameInput::Action { seq, .. } = input else {
+        return (accepted, false);
+    };
+    if !accepted {
+        return (accepted, false);
+    }
+
+    broadcast_state(ruleset, write, &game.state, sessions);
+    if !by_server {
+        let _ = write.send((Audience::Player(player_id), ServerMessage::Ack { seq }));
+    }
+    if game_end {
+        log::info!(
+            "game {} of {} ended with {:?}",
+            game_id,
+            R::NAME,
+            ruleset.scores(&game.state)
+        );
+        set_room_state(game_id, RoomState::Ended);
+        if let Err(error) = save_record(game_id, &game.log, true) {
+            log::error!("failed to save game {}: {}", game_id, error);
+        }
+        // make sure the result is on disk, without holding up the table
+        tokio::spawn(async {
+            if let Err(error) = DATABASE.flush_async().await {
+                log::error!("failed to flush the database: {}", error);
+            }
+        });
+        // the table has its final state, sockets from now on are answered from the log
+        GAME_INFO_STORE.remove(game_id.as_str());
+    }
+    (accepted, game_end)
+}
+
+// appends the entry just pushed to the log on disk
+fn store_last_entry(game_id: &str, log: &GameLog) {
+    let Some(entry) = log.entries.last() else {
+        return;
+    };
+    if let Err(error) = append_entry(game_id, log.entries.len() - 1, entry) {
+        log::error!("failed to save game {}: {}", game_id, error);
+    }
+}
+
+// restarts the actors of every game that was still running when the server went down
+pub fn restore_games() {
+    let games = match load_unfinished_games() {
+        Ok(games) => games,
+        Err(error) => {
+            log::error!("failed to load saved games: {}", error);
+            return;
+        }
+    };
+    for (game_id, log) in games {
+        let Some(ruleset) = find_ruleset(&log.ruleset) else {
+            log::warn!(
+                "not restoring game {} of unknown ruleset {}",
+                game_id,
+                log.ruleset
+            );
+            continue;
+        };
+        // only the log is stored, the state is rebuilt from it
+        let state = match ruleset.replay(&log, log.entries.len()) {
+            Ok(state) => state,
+            Err(error) => {
+                log::warn!("not restoring game {}: {}", game_id, error);
+                continue;
+            }
+        };
+        let mut game = SavedGame { state, log };
+        // sockets did not survive the restart, log that so replays agree
+        for player_id in 0..game.state.players.len() {
+            if game.state.players[player_id].connected {
+                game.state.disconnect(player_id);
+                game.log.push(
+                    SystemClock.now(),
+                    player_id,
+                    GameInput::Disconnect { session: 0 },
+                    true,
+                    Vec::new(),
+                );
+                store_last_entry(&game_id, &game.log);
+            }
+        }
+        let players: Vec<(String, String)> = game
+            .state
+            .players
+            .iter()
+            .map(|player| (player.uuid.clone(), player.name.clone()))
+            .collect();
+        let (config, seed) = (game.log.config.clone(), game.log.seed);
+        restore_room(&game_id, ruleset.name(), &players, config.clone(), seed);
+        ruleset.start(players, config, seed, game_id, Some(game));
+    }
+}
+
+// Sends every connected player what changed since their last state, or the
+// whole state if they have none yet or cannot apply deltas. Each recipient gets
+// a state masked for themselves, so hidden hands and balances never leave the
+// server. Spectators share one snapshot masked for an observer.
+fn broadcast_state<R: Ruleset>(
+    ruleset: &R,
+    write: &GameSender,
+    game_state: &GameState,
+    sessions: &mut Sessions,
+) {
+    for player in game_state.players.iter().filter(|player| player.connected) {
+        let state = ruleset.mask(game_state, Some(player.id));
+        let deltas = sessions.capabilities[player.id] & CAP_STATE_DELTA != 0;
+        let message = match sessions.sent[player.id].take() {
+            Some(previous) if deltas => ServerMessage::StateDelta(previous.diff(&state)),
+            _ => ServerMessage::StateUpdate(state.clone()),
+        };
+        let _ = write.send((Audience::Player(player.id), message));
+        sessions.sent[player.id] = Some(state);
+    }
+    if sessions.spectators > 0 {
+        let state = ruleset.mask(game_state, None);
+        let _ = write.send((Audience::Spectators, ServerMessage::StateUpdate(state)));
+    }
+}
+
+// spectators only ever read, they are counted and sent snapshots
+fn handle_spectator<R: Ruleset, C: Clock>(ctx: &mut GameContext<R, C>, input: SpectatorInput) {
+    let GameContext {
+        ruleset,
+        game,
+        sessions,
+        write,
+        ..
+    } = ctx;
+    let game_state = &game.state;
+    match input {
+        SpectatorInput::Join => sessions.spectators += 1,
+        SpectatorInput::Leave => sessions.spectators = sessions.spectators.saturating_sub(1),
+        SpectatorInput::RequestState { session } => {
+            let state = ruleset.mask(game_state, None);
+            let _ = write.send((
+                Audience::Session(session),
+                ServerMessage::StateUpdate(state),
+            ));
+            let _ = write.send((
+                Audience::Session(session),
+                ServerMessage::SpectatorCount(sessions.spectators),
+            ));
+            return;
+        }
+    }
+    let _ = write.send((
+        Audience::Everyone,
+        ServerMessage::SpectatorCount(sessions.spectators),
+    ));
+}
+
 pub fn force_stop_game(game_id: &str) {
     if let Some((_, game)) = GAME_INFO_STORE.remove(game_id) {
-        game.read.send((None, ServerMessage::GameStop)).unwrap();
+        let _ = game
+            .read
+            .send((Audience::Everyone, ServerMessage::GameStop));
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::{handle_input, handle_spectator, GameContext, GameSender, Sessions};
+    use crate::{
+        common::{
+            config::GameConfig,
+            error::GameError,
+            fixtures::{players, same},
+            input::{ActionInput, GameInput, Seq},
+            server_message::ServerMessage,
+        },
+        server::{
+            clock::ManualClock,
+            database::SavedGame,
+            game_log::GameLog,
+            ruleset::{ModernArt, Ruleset},
+            websocket::{Audience, SpectatorInput},
+        },
+    };
+    use tokio::sync::broadcast::{self, Receiver};
+
+    fn table(write: GameSender) -> GameContext<ModernArt, ManualClock> {
+        let (players, config) = (players(3), GameConfig::default());
+        GameContext {
+            ruleset: ModernArt,
+            game_id: "test-game".to_string(),
+            game: SavedGame {
+                state: ModernArt.setup(players.clone(), config.clone(), 0),
+                log: GameLog::new(ModernArt::NAME, players, config, 0),
+            },
+            sessions: Sessions::new(3),
+            write,
+            clock: ManualClock::new(0.0),
+        }
+    }
+
+    // the ack or rejection player 0 got for `seq`, skipping everything else
+    fn answer(read: &mut Receiver<(Audience, ServerMessage)>, seq: Seq) -> ServerMessage {
+        while let Ok((audience, message)) = read.try_recv() {
+            match message {
+                ServerMessage::Ack { seq: answered }
+                | ServerMessage::Rejected { seq: answered, .. }
+                    if audience == Audience::Player(0) && answered == seq =>
+                {
+                    return message
+                }
+                _ => {}
+            }
+        }
+        panic!("no answer to {}", seq);
+    }
+
+    #[test]
+    fn a_resent_action_is_answered_again_but_not_applied_again() {
+        let (write, mut read) = broadcast::channel(64);
+        let mut ctx = table(write);
+        let card = ctx.game.state.deck[0][0].id;
+        let play = GameInput::Action {
+            seq: 1,
+            input: ActionInput::PlayCard(card),
+        };
+        assert_eq!(handle_input(&mut ctx, 0, play, None), (true, false));
+        assert!(matches!(answer(&mut read, 1), ServerMessage::Ack { .. }));
+        let after = ctx.game.state.clone();
+
+        // the ack was lost, the client sends the same action again
+        assert_eq!(handle_input(&mut ctx, 0, play, None), (false, false));
+        assert!(matches!(answer(&mut read, 1), ServerMessage::Ack { .. }));
+        assert!(same(&ctx.game.state, &after));
+        assert_eq!(ctx.game.log.entries.len(), 1);
+
+        // a refused action resent is not tried again either
+        let refused = GameInput::Action {
+            seq: 2,
+            input: ActionInput::PlayCard(card),
+        };
+        assert_eq!(handle_input(&mut ctx, 0, refused, None), (false, false));
+        assert!(matches!(
+            answer(&mut read, 2),
+            ServerMessage::Rejected { error, .. } if error != GameError::AlreadyHandled
+        ));
+        assert_eq!(handle_input(&mut ctx, 0, refused, None), (false, false));
+        assert!(matches!(
+            answer(&mut read, 2),
+            ServerMessage::Rejected {
+                error: GameError::AlreadyHandled,
+                ..
+            }
+        ));
+        assert!(same(&ctx.game.state, &after));
+        assert_eq!(ctx.game.log.entries.len(), 2);
+    }
+
+    #[test]
+    fn a_spectator_asking_for_the_state_alone_gets_it() {
+        let (write, mut read) = broadcast::channel(64);
+        let mut ctx = table(write);
+        handle_spectator(&mut ctx, SpectatorInput::Join);
+        handle_spectator(&mut ctx, SpectatorInput::RequestState { session: 7 });
+        let mut snapshots = 0;
+        while let Ok((audience, message)) = read.try_recv() {
+            if let ServerMessage::StateUpdate(_) = message {
+                assert_eq!(audience, Audience::Session(7));
+                snapshots += 1;
+            }
+        }
+        assert_eq!(snapshots, 1);
+    }
+}
@@ -0,0 +1,199 @@
+use crate::common::{
+    config::GameConfig,
+    game_state::GameState,
+    input::{GameInput, Seq, SERVER_SEQ},
+    player::PlayerID,
+    server_message::GameEvent,
+};
+use anyhow::{bail, Result};
+
+use super::{clock::FixedClock, ruleset::Ruleset};
+
+// Every input the game actor consumes, in order. Together with the seed, the
+// player list and the rules this is enough to rebuild the game at any step.
+#[derive(Clone, Debug, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+#[archive(check_bytes)]
+pub(crate) struct GameLog {
+    // the name of the ruleset the game is played by
+    pub(crate) ruleset: String,
+    pub(crate) seed: u64,
+    pub(crate) players: Vec<(String, String)>, // (uuid, name)
+    pub(crate) config: GameConfig,
+    pub(crate) entries: Vec<LogEntry>,
+}
+
+#[derive(Clone, Debug, rkyv::Archive, rkyv::Deserialize, rkyv::Serialize)]
+#[archive(check_bytes)]
+pub(crate) struct LogEntry {
+    pub(crate) time: f64,
+    pub(crate) player: PlayerID,
+    pub(crate) input: GameInput,
+    pub(crate) accepted: bool,
+    pub(crate) events: Vec<GameEvent>,
+}
+
+impl GameLog {
+    pub(crate) fn new(
+        ruleset: &str,
+        players: Vec<(String, String)>,
+        config: GameConfig,
+        seed: u64,
+    ) -> Self {
+        Self {
+            ruleset: ruleset.to_string(),
+            seed,
+            players,
+            config,
+            entries: Vec::new(),
+        }
+    }
+
+    // everything but the entries
+    pub(crate) fn header(&self) -> GameLog {
+        GameLog {
+            ruleset: self.ruleset.clone(),
+            seed: self.seed,
+            players: self.players.clone(),
+            config: self.config.clone(),
+            entries: Vec::new(),
+        }
+    }
+
+    pub(crate) fn push(
+        &mut self,
+        time: f64,
+        player: PlayerID,
+        input: GameInput,
+        accepted: bool,
+        events: Vec<GameEvent>,
+    ) {
+        self.entries.push(LogEntry {
+            time,
+            player,
+            input,
+            accepted,
+            events,
+        });
+    }
+
+    // the highest sequence number `player` has sent, resends never exceed it
+    pub(crate) fn last_seq(&self, player: PlayerID) -> Seq {
+        self.entries
+            .iter()
+            .filter(|entry| entry.player == player)
+            .filter_map(|entry| match entry.input {
+                GameInput::Action { seq, .. } => Some(seq),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(SERVER_SEQ)
+    }
+
+    pub(crate) fn was_accepted(&self, player: PlayerID, seq: Seq) -> bool {
+        self.entries.iter().any(|entry| {
+            entry.player == player
+                && entry.accepted
+                && matches!(entry.input, GameInput::Action { seq: logged, .. } if logged == seq)
+        })
+    }
+
+    // rebuilds the state right after the first `step` entries have been applied
+    pub(crate) fn replay<R: Ruleset>(&self, ruleset: &R, step: usize) -> Result<GameState> {
+        if self.ruleset != R::NAME {
+            bail!("This game is played by {}.", self.ruleset);
+        }
+        if step > self.entries.len() {
+            bail!("The log only has {} entries.", self.entries.len());
+        }
+        let mut game_state = ruleset.setup(self.players.clone(), self.config.clone(), self.seed);
+        for (i, entry) in self.entries[..step].iter().enumerate() {
+            match entry.input {
+                GameInput::Connect { .. } => {
+                    game_state.connect(entry.player);
+                }
+                GameInput::Disconnect { .. } => game_state.disconnect(entry.player),
+                GameInput::RequestState => {}
+                GameInput::Action { input, .. } => {
+                    if !entry.accepted {
+                        continue;
+                    }
+                    if let Err(error) = ruleset.process_input(
+                        &mut game_state,
+                        entry.player,
+                        input,
+                        &FixedClock(entry.time),
+                    ) {
+                        bail!(
+                            "Entry {} was accepted live but fails on replay: {}",
+                            i,
+                            error
+                        );
+                    }
+                }
+            }
+        }
+        Ok(game_state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GameLog;
+    use crate::{
+        common::{
+            config::GameConfig,
+            fixtures::{players, random_input, same},
+            input::GameInput,
+        },
+        server::{
+            clock::FixedClock,
+            ruleset::{ModernArt, Ruleset},
+        },
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn replay_rebuilds_the_live_game_at_every_step() {
+        let ruleset = ModernArt;
+        // a short game, every step is replayed from the start
+        let config = GameConfig {
+            rounds: 2,
+            round_end_count: 3,
+            deal_schedule: vec![(3, vec![6, 3])],
+            ..GameConfig::default()
+        };
+        let mut log = GameLog::new(ModernArt::NAME, players(3), config, 7);
+        let mut state = ruleset.setup(log.players.clone(), log.config.clone(), log.seed);
+        let mut live = vec![state.clone()];
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut seqs = [0; 3];
+        let mut time = 0.0;
+        while !state.ended && live.len() <= 2000 {
+            let Some((player, input)) = random_input(&state, &mut rng) else {
+                break;
+            };
+            // too little for every free auction window, so some calls are refused
+            time += 3.0;
+            seqs[player] += 1;
+            let result = ruleset.process_input(&mut state, player, input, &FixedClock(time));
+            let accepted = result.is_ok();
+            log.push(
+                time,
+                player,
+                GameInput::Action {
+                    seq: seqs[player],
+                    input,
+                },
+                accepted,
+                result.unwrap_or_default(),
+            );
+            live.push(state.clone());
+        }
+        assert!(state.ended, "no end after {} inputs", log.entries.len());
+        assert!(log.entries.iter().any(|entry| !entry.accepted));
+        for (step, expected) in live.iter().enumerate() {
+            let replayed = log.replay(&ruleset, step).unwrap();
+            assert!(same(&replayed, expected), "step {}", step);
+        }
+    }
+}
@@ -1,20 +1,17 @@
 use crate::common::{
-    card::{AuctionType, Card, CardColor},
-    game_state::{AuctionState, AuctionTarget, GameStage, GameState, Money, ShouldEnd},
-    input::{ActionInput, BidOptionalInner, CardID, MarkedReactionInner, PlayCardOptionalInner},
+    config::GameConfig,
+    game_state::{AuctionState, GameStage, GameState, Money, RoundBoard},
     player::{Player, PlayerID},
-    server_message::GameEvent,
 };
-use anyhow::{bail, Context, Result};
-use itertools::Itertools;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 
-use super::card::{pick, CARD_LIST};
+use super::card::pick;
 
 impl GameState {
-    pub fn mask(&self, player: PlayerID) -> GameState {
+    // sealed bids stay with the server until the host calls
+    fn mask_stage(&self) -> GameStage {
         let stage = self.stage.clone();
-        let stage = if let GameStage::AuctionInAction { ref state, target } = stage {
+        if let GameStage::AuctionInAction { ref state, target } = stage {
             if let AuctionState::Fist {
                 host, action_taken, ..
             } = state
@@ -32,7 +29,39 @@ impl GameState {
             }
         } else {
             stage
-        };
+        }
+    }
+
+    // what a spectator may see: no hands, balances or uuids
+    pub fn mask_observer(&self) -> GameState {
+        GameState {
+            deck: vec![Vec::new()],
+            money: vec![0],
+            owned_cards: self.owned_cards.clone(),
+            players: self
+                .players
+                .iter()
+                .cloned()
+                .map(|mut each| {
+                    each.uuid = "".to_string();
+                    each
+                })
+                .collect(),
+            stage: self.mask_stage(),
+            current_round: self.current_round,
+            round_board: self.round_board.clone(),
+            values: self.values,
+            pool: Vec::new(),
+            discard: self.discard.clone(),
+            issued: 0,
+            ended: self.ended,
+            version: self.version,
+            config: self.config.clone(),
+        }
+    }
+
+    pub fn mask(&self, player: PlayerID) -> GameState {
+        let stage = self.mask_stage();
 
         // at this point player should only look for index 0 for their deck and balance
         GameState {
@@ -54,559 +83,41 @@ impl GameState {
                 .collect(),
             stage,
             current_round: self.current_round,
+            round_board: self.round_board.clone(),
             values: self.values,
             pool: Vec::new(),
-            ended: false,
-        }
-    }
-
-    fn end_round(&mut self) {
-        // set values
-        let rankings: Vec<CardColor> = self
-            .owned_cards
-            .iter()
-            .flatten()
-            .group_by(|card| card.color)
-            .into_iter()
-            .map(|(color, group)| (color, group.count()))
-            .sorted_by_key(|(_, count)| *count)
-            .map(|x| x.0)
-            .collect();
-        let top = &rankings[0..2];
-        // self.values[self.current_round][top[0].0.index()] = 30;
-        // self.values[self.current_round][top[1].0.index()] = 20;
-        // self.values[self.current_round][top[2].0.index()] = 10;
-        for i in 0..3 {
-            self.values[self.current_round][top[i].index()] = (3 - i as u32) * 10;
-        }
-
-        self.sell_cards(top);
-    }
-
-    fn sell_cards(&mut self, target_colors: &[CardColor]) {
-        let current_total_values: Vec<Money> = (0..5)
-            .map(|i| {
-                (0..4)
-                    .map(|round| self.values[round][i])
-                    .reduce(|x, y| x + y)
-                    .unwrap()
-            })
-            .collect();
-        // remove cards
-        for player_id in 0..self.players.len() {
-            let mut earn = 0;
-            self.owned_cards[player_id].retain(|card| {
-                if target_colors
-                    .iter()
-                    .find(|color| card.color == **color)
-                    .is_some()
-                {
-                    earn += current_total_values[card.color.index()];
-                    false
-                } else {
-                    true
-                }
-            });
-            // increase balance
-            self.money[player_id] += earn as u32;
-        }
-    }
-
-    pub(self) fn get_next_player_rounded(&self, current: PlayerID) -> PlayerID {
-        let mut next = current + 1;
-        if next > self.players.len() - 1 {
-            next = 0;
-        }
-        next
-    }
-
-    pub(self) fn get_next_player(&self, starter: PlayerID, current: PlayerID) -> Option<PlayerID> {
-        let next = self.get_next_player_rounded(current);
-        if next == starter {
-            return None;
-        } else {
-            return Some(next);
-        }
-    }
-
-    fn test_enough_money(&self, player: PlayerID, money: Money) -> Result<()> {
-        if self.money.get(player).unwrap() > &money {
-            Ok(())
-        } else {
-            bail!("Not enough money.");
-        }
-    }
-
-    fn get_card(&self, player_id: PlayerID, card_id: CardID) -> Result<&Card> {
-        self.deck
-            .get(player_id)
-            .unwrap()
-            .iter()
-            .filter(|card| card.id == card_id)
-            .next()
-            .context("No such card.")
-    }
-
-    fn complete_transaction(
-        &mut self,
-        target: AuctionTarget,
-        money: Money,
-        current: PlayerID,
-    ) -> GameStage {
-        *self.money.get_mut(current).unwrap() -= money;
-        let owner;
-        match target {
-            AuctionTarget::Single((starter, card)) => {
-                self.owned_cards[current].push(card);
-                owner = starter;
-            }
-            AuctionTarget::Double {
-                double_card: (_, double_card),
-                target_card: (starter, target_card),
-            } => {
-                self.owned_cards[current].push(double_card);
-                self.owned_cards[current].push(target_card);
-                owner = starter;
-            }
+            discard: self.discard.clone(),
+            issued: 0,
+            ended: self.ended,
+            version: self.version,
+            config: self.config.clone(),
         }
-        if owner != current {
-            *self.money.get_mut(owner).unwrap() += money;
-        }
-        let next = self.get_next_player_rounded(owner);
-        GameStage::WaitingForNextCard(next)
     }
 
-    // TODO: emit finer GameEvent
-    pub fn process_input(
-        &mut self,
-        from: PlayerID,
-        input: ActionInput,
-    ) -> Result<Option<GameEvent>> {
-        let mut event = None;
-        let next_stage = match (&self.stage, input) {
-            (GameStage::WaitingForNextCard(player_id), ActionInput::PlayCard(card_id)) => {
-                if *player_id == from {
-                    let card =
-                        play_card(&mut self.deck, self.players.get_mut(from).unwrap(), card_id)?;
-                    match self.should_end() {
-                        ShouldEnd::RoundEnd => {
-                            event = Some(GameEvent::RoundEnd);
-                            self.end_round();
-                            let next = self.get_next_player_rounded(from);
-                            GameStage::WaitingForNextCard(next)
-                            // TODO: end round && clean up
-                        }
-                        ShouldEnd::GameEnd => {
-                            event = Some(GameEvent::GameEnd);
-                            self.end_round();
-                            self.sell_cards(&[
-                                CardColor::Red,
-                                CardColor::Green,
-                                CardColor::Blue,
-                                CardColor::Purple,
-                                CardColor::Yellow,
-                            ]);
-                            let next = self.get_next_player_rounded(from);
-                            GameStage::WaitingForNextCard(next)
-                        }
-                        ShouldEnd::No => {
-                            if let AuctionType::Double = card.ty {
-                                let next = self.get_next_player_rounded(from);
-                                GameStage::WaitingForDoubleTarget {
-                                    double_card: (from, card),
-                                    current: next,
-                                }
-                            } else if let AuctionType::Marked = card.ty {
-                                GameStage::WaitingForMarkedPrice {
-                                    starter: from,
-                                    target: AuctionTarget::Single((from, card)),
-                                }
-                            } else {
-                                let state = self.gen_auction_state(&card, from, self.players.len());
-                                GameStage::AuctionInAction {
-                                    state,
-                                    target: AuctionTarget::Single((from, card)),
-                                }
-                            }
-                        }
-                    }
-                } else {
-                    bail!("Not your turn yet.");
-                }
-            }
-            (
-                GameStage::WaitingForDoubleTarget {
-                    double_card,
-                    current,
-                },
-                ActionInput::PlayCardOptional(inner),
-            ) => {
-                if *current == from {
-                    match inner {
-                        PlayCardOptionalInner::Pass => {
-                            match self.get_next_player(double_card.0, *current) {
-                                Some(next) => GameStage::WaitingForDoubleTarget {
-                                    double_card: *double_card,
-                                    current: next,
-                                },
-                                None => {
-                                    //TODO: announce free get
-                                    let next = self.get_next_player_rounded(from);
-                                    GameStage::WaitingForNextCard(next)
-                                }
-                            }
-                        }
-                        PlayCardOptionalInner::Play(card_id) => {
-                            let card = self.get_card(from, card_id)?;
-                            if card.color != double_card.1.color {
-                                bail!("Wrong card color.");
-                            }
-                            if let AuctionType::Double = card.ty {
-                                bail!("Cannot set another double card as the target to a previous double card.");
-                            }
-                            let card = play_card(
-                                &mut self.deck,
-                                self.players.get_mut(from).unwrap(),
-                                card_id,
-                            )?;
-                            match self.should_end() {
-                                ShouldEnd::RoundEnd => {
-                                    event = Some(GameEvent::RoundEnd);
-                                    self.end_round();
-                                    let next = self.get_next_player_rounded(from);
-                                    GameStage::WaitingForNextCard(next)
-                                    // TODO: end round && clean up
-                                }
-                                ShouldEnd::GameEnd => {
-                                    event = Some(GameEvent::GameEnd);
-                                    self.end_round();
-                                    self.sell_cards(&[
-                                        CardColor::Red,
-                                        CardColor::Green,
-                                        CardColor::Blue,
-                                        CardColor::Purple,
-                                        CardColor::Yellow,
-                                    ]);
-                                    let next = self.get_next_player_rounded(from);
-                                    GameStage::WaitingForNextCard(next)
-                                }
-                                ShouldEnd::No => {
-                                    if let AuctionType::Marked = card.ty {
-                                        GameStage::WaitingForMarkedPrice {
-                                            starter: from,
-                                            target: AuctionTarget::Double {
-                                                double_card: *double_card,
-                                                target_card: (from, card),
-                                            },
-                                        }
-                                    } else {
-                                        let state =
-                                            self.gen_auction_state(&card, from, self.players.len());
-                                        GameStage::AuctionInAction {
-                                            state,
-                                            target: AuctionTarget::Double {
-                                                double_card: *double_card,
-                                                target_card: (from, card),
-                                            },
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                } else {
-                    bail!("Not your turn yet.");
-                }
-            }
-            (
-                GameStage::WaitingForMarkedPrice { starter, target },
-                ActionInput::AssignMarkedPrice(money),
-            ) => {
-                if *starter == from {
-                    self.test_enough_money(from, money)?;
-                    let next = self.get_next_player_rounded(from);
-                    GameStage::AuctionInAction {
-                        state: AuctionState::Marked {
-                            current: next,
-                            price: (from, money),
-                        },
-                        target: *target,
-                    }
-                } else {
-                    bail!("Not your turn yet.");
-                }
-            }
-            (GameStage::AuctionInAction { state, target }, ActionInput::Bid(money)) => {
-                self.test_enough_money(from, money)?;
-                match state {
-                    AuctionState::Free { host, highest, .. } => {
-                        if money > highest.1 {
-                            let highest = (from, money);
-                            let calls = 0;
-                            let now = SystemTime::now();
-                            let time_end = now + Duration::from_secs(3);
-                            GameStage::AuctionInAction {
-                                state: AuctionState::Free {
-                                    host: *host,
-                                    highest,
-                                    time_end: time_end
-                                        .duration_since(UNIX_EPOCH)
-                                        .unwrap()
-                                        .as_secs_f64(),
-                                    calls,
-                                },
-                                target: *target,
-                            }
-                        } else {
-                            bail!("The current price is higher than your offer.");
-                        }
-                    }
-                    AuctionState::Fist {
-                        host,
-                        bids,
-                        action_taken,
-                    } => {
-                        let mut bids = bids.clone();
-                        *bids.get_mut(from).unwrap() = money;
-                        let mut action_taken = action_taken.clone();
-                        *action_taken.get_mut(from).unwrap() = true;
-                        GameStage::AuctionInAction {
-                            state: AuctionState::Fist {
-                                host: *host,
-                                bids,
-                                action_taken,
-                            },
-                            target: *target,
-                        }
-                    }
-                    _ => {
-                        bail!("Invalid action.");
-                    }
-                }
-            }
-            (GameStage::AuctionInAction { state, target }, ActionInput::BidOptional(inner)) => {
-                match state {
-                    AuctionState::Circle {
-                        starter,
-                        current_player,
-                        highest,
-                    } => {
-                        if *current_player == from {
-                            match inner {
-                                BidOptionalInner::Pass => {
-                                    if *current_player == *starter {
-                                        event = Some(GameEvent::AuctionComplete {
-                                            target: *target,
-                                            buyer: *highest,
-                                            seller: *starter,
-                                        });
-                                        self.complete_transaction(*target, highest.1, highest.0)
-                                    } else {
-                                        let next = self.get_next_player_rounded(*current_player);
-                                        GameStage::AuctionInAction {
-                                            state: AuctionState::Circle {
-                                                starter: *starter,
-                                                current_player: next,
-                                                highest: *highest,
-                                            },
-                                            target: *target,
-                                        }
-                                    }
-                                }
-                                BidOptionalInner::Bid(money) => {
-                                    if money < highest.1 {
-                                        bail!("The current price is higher than your offer.");
-                                    }
-                                    let highest = (from, money);
-                                    if *current_player == *starter {
-                                        event = Some(GameEvent::AuctionComplete {
-                                            target: *target,
-                                            buyer: highest,
-                                            seller: *starter,
-                                        });
-                                        self.complete_transaction(*target, highest.1, highest.0)
-                                    } else {
-                                        let next = self.get_next_player_rounded(*current_player);
-                                        GameStage::AuctionInAction {
-                                            state: AuctionState::Circle {
-                                                starter: *starter,
-                                                current_player: next,
-                                                highest,
-                                            },
-                                            target: *target,
-                                        }
-                                    }
-                                }
-                            }
-                        } else {
-                            bail!("Not your turn yet.");
-                        }
-                    }
-                    _ => {
-                        bail!("Invalid action.");
-                    }
-                }
-            }
-            (GameStage::AuctionInAction { state, target }, ActionInput::MarkedReaction(inner)) => {
-                match state {
-                    AuctionState::Marked { current, price } => {
-                        if *current == from {
-                            if price.0 == *current {
-                                event = Some(GameEvent::AuctionComplete {
-                                    target: *target,
-                                    buyer: (*current, price.1),
-                                    seller: price.0,
-                                });
-                                self.complete_transaction(*target, price.1, from)
-                            } else {
-                                match inner {
-                                    MarkedReactionInner::Accept => {
-                                        self.test_enough_money(from, price.1)?;
-                                        event = Some(GameEvent::AuctionComplete {
-                                            target: *target,
-                                            buyer: (*current, price.1),
-                                            seller: price.0,
-                                        });
-                                        self.complete_transaction(*target, price.1, from)
-                                    }
-                                    MarkedReactionInner::Pass => {
-                                        let next = self.get_next_player_rounded(*current);
-                                        GameStage::AuctionInAction {
-                                            state: AuctionState::Marked {
-                                                current: next,
-                                                price: *price,
-                                            },
-                                            target: *target,
-                                        }
-                                    }
-                                }
-                            }
-                        } else {
-                            bail!("Not your turn yet.");
-                        }
-                    }
-                    _ => {
-                        bail!("Invalid action.");
-                    }
-                }
-            }
-            (GameStage::AuctionInAction { state, target }, ActionInput::Call) => match state {
-                AuctionState::Free {
-                    host,
-                    highest,
-                    time_end,
-                    calls,
-                } => {
-                    if *host == from {
-                        let now = SystemTime::now()
-                            .duration_since(UNIX_EPOCH)
-                            .unwrap()
-                            .as_secs_f64();
-                        if now > *time_end {
-                            if *calls == 2 {
-                                event = Some(GameEvent::AuctionComplete {
-                                    target: *target,
-                                    buyer: *highest,
-                                    seller: *host,
-                                });
-                                self.complete_transaction(*target, highest.1, highest.0)
-                            } else {
-                                let calls = calls + 1;
-                                let now = SystemTime::now();
-                                let time_end = now + Duration::from_secs(3);
-                                GameStage::AuctionInAction {
-                                    state: AuctionState::Free {
-                                        host: *host,
-                                        highest: *highest,
-                                        time_end: time_end
-                                            .duration_since(UNIX_EPOCH)
-                                            .unwrap()
-                                            .as_secs_f64(),
-                                        calls,
-                                    },
-                                    target: *target,
-                                }
-                            }
-                        } else {
-                            bail!("Please wait for at least 3 seconds before calling.");
-                        }
-                    } else {
-                        bail!("Not your turn yet.");
-                    }
-                }
-                AuctionState::Fist {
-                    host,
-                    bids,
-                    action_taken,
-                } => {
-                    if from != *host {
-                        bail!("Invalid action.");
-                    }
-                    if action_taken.contains(&false) {
-                        bail!("Somebody has not made their decision yet!");
-                    }
-                    let max = bids.iter().max().unwrap();
-                    let max_index = bids.iter().position(|bid| *bid == *max).unwrap();
-                    event = Some(GameEvent::AuctionComplete {
-                        target: *target,
-                        buyer: (max_index, *max),
-                        seller: *host,
-                    });
-                    self.complete_transaction(*target, *max, max_index)
-                }
-                _ => {
-                    bail!("Invalid action.");
-                }
-            },
-            _ => {
-                bail!("Invalid action.");
-            }
-        };
-        self.stage = next_stage;
-
-        Ok(event)
+    // returns false if the player was already connected, from another socket
+    pub fn connect(&mut self, player: PlayerID) -> bool {
+        let player = &mut self.players[player];
+        !std::mem::replace(&mut player.connected, true)
     }
 
-    fn gen_auction_state(&self, card: &Card, from: PlayerID, player_count: usize) -> AuctionState {
-        match card.ty {
-            AuctionType::Free => {
-                let now = SystemTime::now();
-                let time_end = now + Duration::from_secs(3);
-                AuctionState::Free {
-                    host: from,
-                    highest: (from, 0 as Money),
-                    time_end: time_end.duration_since(UNIX_EPOCH).unwrap().as_secs_f64(),
-                    calls: 0,
-                }
-            }
-            AuctionType::Circle => AuctionState::Circle {
-                starter: from,
-                current_player: self.get_next_player_rounded(from),
-                highest: (from, 0 as Money),
-            },
-            AuctionType::Fist => AuctionState::Fist {
-                host: from,
-                bids: vec![0 as Money; player_count],
-                action_taken: vec![false; player_count],
-            },
-            _ => unreachable!(),
-        }
+    pub fn disconnect(&mut self, player: PlayerID) {
+        self.players[player].connected = false;
     }
 }
 
-fn play_card(deck: &mut Vec<Vec<Card>>, from: &mut Player, card_id: CardID) -> Result<Card> {
-    let player_deck = deck.get_mut(from.id).unwrap();
-    let index = player_deck
-        .iter()
-        .position(|card| card.id == card_id)
-        .context("No such card.")?;
-    let card = player_deck.remove(index);
-    Ok(card)
-}
-
 impl GameState {
     // Vec<(uuid, name)>
-    pub fn new(players: Vec<(String, String)>) -> Self {
+    // the same seed always deals the same hands, so seeds can be shared;
+    // `config` has to be valid for this many players
+    pub fn new(players: Vec<(String, String)>, config: GameConfig, seed: u64) -> Self {
+        Self::with_rng(players, config, &mut StdRng::seed_from_u64(seed))
+    }
+
+    pub fn with_rng(
+        players: Vec<(String, String)>,
+        config: GameConfig,
+        rng: &mut impl Rng,
+    ) -> Self {
         let player_count = players.len();
         let players = players
             .into_iter()
@@ -618,22 +129,49 @@ impl GameState {
                 connected: false,
             })
             .collect();
-        let mut pool = CARD_LIST.to_vec();
+        let mut pool = config.cards();
         let deck = (0..player_count)
-            .map(|_| pick(&mut pool, 13 - player_count))
+            .map(|_| pick(rng, &mut pool, config.cards_to_deal(player_count, 0)))
             .collect();
+        // later rounds deal off the top, so the seed alone decides every hand
+        pool.shuffle(rng);
 
         Self {
             players,
             deck,
             owned_cards: vec![Vec::new(); player_count],
-            money: vec![100; player_count],
+            money: vec![config.starting_money; player_count],
             stage: GameStage::WaitingForNextCard(0),
             current_round: 0,
+            round_board: RoundBoard::default(),
             values: [[0; 5]; 5],
             pool,
+            discard: Vec::new(),
+            issued: config.starting_money * player_count as Money,
             ended: false,
+            version: 0,
+            config,
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use crate::common::{
+        config::presets,
+        fixtures::{players, same},
+        game_state::GameState,
+    };
+
+    #[test]
+    fn the_same_seed_and_config_deal_the_same_game() {
+        for (name, config) in presets() {
+            for count in 3..=5 {
+                let deal = |seed| GameState::new(players(count), config.clone(), seed);
+                // hands and the pool the later rounds are dealt from
+                assert!(same(&deal(42), &deal(42)), "{} for {}", name, count);
+                assert!(!same(&deal(42), &deal(43)), "{} for {}", name, count);
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
 pub mod identicon;
 pub mod player;
+pub mod replay;
 pub mod room;
 
 #[cfg(feature = "ssr")]
@@ -8,9 +9,30 @@ pub mod websocket;
 #[cfg(feature = "ssr")]
 pub mod game;
 
+#[cfg(feature = "ssr")]
+pub mod bot;
+
 #[cfg(feature = "ssr")]
 pub mod card;
 
+#[cfg(feature = "ssr")]
+pub mod clock;
+
+#[cfg(feature = "ssr")]
+pub mod database;
+
+// only for tests and `cargo run --example fuzz --features fuzz`
+#[cfg(all(feature = "ssr", any(test, feature = "fuzz")))]
+pub mod fuzz;
+
+#[cfg(feature = "ssr")]
+pub mod game_log;
+
 #[cfg(feature = "ssr")]
 pub mod game_state;
 
+#[cfg(feature = "ssr")]
+pub mod ruleset;
+
+#[cfg(feature = "ssr")]
+pub mod timer;
@@ -19,4 +19,3 @@ pub async fn get_player_name(uuid: String, game_id: String) -> Result<String, Se
                 .map_or("".to_string(), |player| player.1.clone())
         }))
 }
-
@@ -0,0 +1,41 @@
+use leptos::*;
+
+// Rebuilds a game at `step` from its log for post-game review. Returns the number
+// of log entries and the rkyv-encoded `GameState`, which is masked for the
+// requester until the game has ended.
+#[server(GetReplay, "/api", "GetCbor", "replay")]
+pub async fn get_replay(
+    uuid: String,
+    game_id: String,
+    step: usize,
+) -> Result<(usize, Vec<u8>), ServerFnError> {
+    use super::{database::load_game, ruleset::find_ruleset};
+    use rkyv::to_bytes;
+
+    let (log, ended) = load_game(&game_id)
+        .map_err(|e| ServerFnError::ServerError(e.to_string()))?
+        .ok_or_else(|| ServerFnError::ServerError("No such game.".to_string()))?;
+    let player_id = log
+        .players
+        .iter()
+        .position(|player| player.0 == uuid)
+        .ok_or_else(|| {
+            ServerFnError::ServerError("You did not take part in this game.".to_string())
+        })?;
+    let ruleset = find_ruleset(&log.ruleset).ok_or_else(|| {
+        ServerFnError::ServerError(format!("This server cannot replay {}.", log.ruleset))
+    })?;
+    let state = ruleset
+        .replay(&log, step)
+        .map_err(|e| ServerFnError::ServerError(e.to_string()))?;
+    let state = if ended {
+        state
+    } else {
+        ruleset.mask(&state, Some(player_id))
+    };
+    let data = to_bytes::<_, 4096>(&state)
+        .map_err(|e| ServerFnError::ServerError(e.to_string()))?
+        .to_vec();
+
+    Ok((log.entries.len(), data))
+}
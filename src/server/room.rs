@@ -1,6 +1,437 @@
+use crate::common::config::GameConfig;
+pub use crate::common::config::{MAX_PLAYERS, MIN_PLAYERS};
+use leptos::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RoomState {
     Waiting,
     Started,
     Ended,
 }
 
+// what clients get to see of a room, uuids never leave the server
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RoomInfo {
+    pub id: String,
+    pub name: String,
+    pub players: Vec<RoomPlayerInfo>,
+    pub host: usize,
+    pub state: RoomState,
+    pub self_index: Option<usize>,
+    pub config: GameConfig,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoomPlayerInfo {
+    pub name: String,
+    pub ready: bool,
+    pub bot: bool,
+}
+
+impl RoomInfo {
+    pub fn is_host(&self) -> bool {
+        self.self_index.is_some_and(|index| index == self.host)
+    }
+
+    pub fn can_start(&self) -> bool {
+        self.config.validate(self.players.len()).is_ok()
+            && self
+                .players
+                .iter()
+                .enumerate()
+                .all(|(i, player)| i == self.host || player.ready)
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub struct Room {
+    pub name: String,
+    pub players: Vec<RoomPlayer>,
+    pub host: usize,
+    pub state: RoomState,
+    // the name of the ruleset the game will be played by
+    pub ruleset: &'static str,
+    pub config: GameConfig,
+    // what the game is dealt from, drawn with the room and never shown to players
+    pub seed: u64,
+}
+
+#[cfg(feature = "ssr")]
+pub struct RoomPlayer {
+    pub uuid: String,
+    pub name: String,
+    pub ready: bool,
+}
+
+#[cfg(feature = "ssr")]
+impl Room {
+    pub fn info(&self, id: &str, uuid: &str) -> RoomInfo {
+        RoomInfo {
+            id: id.to_string(),
+            name: self.name.clone(),
+            players: self
+                .players
+                .iter()
+                .map(|player| RoomPlayerInfo {
+                    name: player.name.clone(),
+                    ready: player.ready,
+                    bot: super::bot::is_bot(&player.uuid),
+                })
+                .collect(),
+            host: self.host,
+            state: self.state,
+            self_index: self.players.iter().position(|player| player.uuid == uuid),
+            config: self.config.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub static ROOM_STORE: once_cell::sync::Lazy<dashmap::DashMap<String, Room>> =
+    once_cell::sync::Lazy::new(dashmap::DashMap::new);
+
+// how long an ended room stays around, so everyone can still find it
+#[cfg(feature = "ssr")]
+const ENDED_ROOM_LINGER: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+#[cfg(feature = "ssr")]
+pub fn set_room_state(room_id: &str, state: RoomState) {
+    if let Some(mut room) = ROOM_STORE.get_mut(room_id) {
+        room.state = state;
+    }
+    if state == RoomState::Ended {
+        // nothing left to restore
+        if let Err(error) = super::database::remove_room(room_id) {
+            log::error!("failed to remove room {}: {}", room_id, error);
+        }
+        let room_id = room_id.to_string();
+        tokio::spawn(async move {
+            tokio::time::sleep(ENDED_ROOM_LINGER).await;
+            ROOM_STORE.remove_if(&room_id, |_, room| room.state == RoomState::Ended);
+        });
+    }
+}
+
+// Puts the room of a game restored after a restart back, so the lobby lists it
+// and its end is recorded. Rooms saved by an older server get a stand-in name.
+#[cfg(feature = "ssr")]
+pub fn restore_room(
+    room_id: &str,
+    ruleset: &'static str,
+    players: &[(String, String)],
+    config: GameConfig,
+    seed: u64,
+) {
+    let record = super::database::load_room(room_id).unwrap_or_else(|error| {
+        log::warn!("failed to load room {}: {}", room_id, error);
+        None
+    });
+    let (name, host) = record
+        .map(|record| (record.name, record.host))
+        .unwrap_or_else(|| (format!("Game {}", room_id), 0));
+    ROOM_STORE.insert(
+        room_id.to_string(),
+        Room {
+            name,
+            players: players
+                .iter()
+                .map(|(uuid, name)| RoomPlayer {
+                    uuid: uuid.clone(),
+                    name: name.clone(),
+                    ready: true,
+                })
+                .collect(),
+            host,
+            state: RoomState::Started,
+            ruleset,
+            config,
+            seed,
+        },
+    );
+}
+
+#[server(ListRooms, "/api", "GetCbor", "list_rooms")]
+pub async fn list_rooms(uuid: String) -> Result<Vec<RoomInfo>, ServerFnError> {
+    Ok(ROOM_STORE
+        .iter()
+        .filter(|room| room.state != RoomState::Ended)
+        .map(|room| room.info(room.key(), &uuid))
+        .collect())
+}
+
+#[server(GetRoom, "/api", "GetCbor", "get_room")]
+pub async fn get_room(uuid: String, room_id: String) -> Result<RoomInfo, ServerFnError> {
+    ROOM_STORE
+        .get(&room_id)
+        .map(|room| room.info(&room_id, &uuid))
+        .ok_or_else(|| ServerFnError::ServerError("No such room.".to_string()))
+}
+
+#[server(CreateRoom, "/api", "Cbor", "create_room")]
+pub async fn create_room(
+    uuid: String,
+    player_name: String,
+    room_name: String,
+) -> Result<String, ServerFnError> {
+    use nanoid::nanoid;
+
+    if player_name.trim().is_empty() || room_name.trim().is_empty() {
+        return Err(ServerFnError::ServerError(
+            "Names cannot be empty.".to_string(),
+        ));
+    }
+    let room_id = nanoid!(8);
+    ROOM_STORE.insert(
+        room_id.clone(),
+        Room {
+            name: room_name.trim().to_string(),
+            players: vec![RoomPlayer {
+                uuid,
+                name: player_name.trim().to_string(),
+                ready: false,
+            }],
+            host: 0,
+            state: RoomState::Waiting,
+            ruleset: super::ruleset::default_ruleset().name(),
+            config: GameConfig::default(),
+            seed: rand::random(),
+        },
+    );
+    Ok(room_id)
+}
+
+#[server(JoinRoom, "/api", "Cbor", "join_room")]
+pub async fn join_room(
+    uuid: String,
+    player_name: String,
+    room_id: String,
+) -> Result<(), ServerFnError> {
+    let mut room = ROOM_STORE
+        .get_mut(&room_id)
+        .ok_or_else(|| ServerFnError::ServerError("No such room.".to_string()))?;
+    if room.players.iter().any(|player| player.uuid == uuid) {
+        return Ok(());
+    }
+    if room.state != RoomState::Waiting {
+        return Err(ServerFnError::ServerError(
+            "This room has already started.".to_string(),
+        ));
+    }
+    if room.players.len() >= MAX_PLAYERS {
+        return Err(ServerFnError::ServerError("This room is full.".to_string()));
+    }
+    if player_name.trim().is_empty() {
+        return Err(ServerFnError::ServerError(
+            "Names cannot be empty.".to_string(),
+        ));
+    }
+    if room
+        .players
+        .iter()
+        .any(|player| player.name == player_name.trim())
+    {
+        return Err(ServerFnError::ServerError(
+            "This name is already taken.".to_string(),
+        ));
+    }
+    room.players.push(RoomPlayer {
+        uuid,
+        name: player_name.trim().to_string(),
+        ready: false,
+    });
+    Ok(())
+}
+
+#[server(LeaveRoom, "/api", "Cbor", "leave_room")]
+pub async fn leave_room(uuid: String, room_id: String) -> Result<(), ServerFnError> {
+    use super::bot::is_bot;
+
+    let remove = {
+        let mut room = ROOM_STORE
+            .get_mut(&room_id)
+            .ok_or_else(|| ServerFnError::ServerError("No such room.".to_string()))?;
+        if room.state != RoomState::Waiting {
+            return Err(ServerFnError::ServerError(
+                "Cannot leave a started room.".to_string(),
+            ));
+        }
+        if let Some(index) = room.players.iter().position(|player| player.uuid == uuid) {
+            room.players.remove(index);
+            // hand the room over to the next player in line
+            if index < room.host || room.host >= room.players.len() {
+                room.host = room.host.saturating_sub(1);
+            }
+            // bots cannot host, pass it on to a human if any are left
+            if let Some(human) = room.players.iter().position(|player| !is_bot(&player.uuid)) {
+                if is_bot(&room.players[room.host].uuid) {
+                    room.host = human;
+                }
+            }
+        }
+        room.players.iter().all(|player| is_bot(&player.uuid))
+    };
+    if remove {
+        ROOM_STORE.remove(&room_id);
+    }
+    Ok(())
+}
+
+#[server(SetReady, "/api", "Cbor", "set_ready")]
+pub async fn set_ready(uuid: String, room_id: String, ready: bool) -> Result<(), ServerFnError> {
+    let mut room = ROOM_STORE
+        .get_mut(&room_id)
+        .ok_or_else(|| ServerFnError::ServerError("No such room.".to_string()))?;
+    let player = room
+        .players
+        .iter_mut()
+        .find(|player| player.uuid == uuid)
+        .ok_or_else(|| ServerFnError::ServerError("You are not in this room.".to_string()))?;
+    player.ready = ready;
+    Ok(())
+}
+
+// fills an empty seat with a computer player, bots are always ready
+#[server(AddBot, "/api", "Cbor", "add_bot")]
+pub async fn add_bot(uuid: String, room_id: String) -> Result<(), ServerFnError> {
+    use super::bot::{bot_name, BOT_UUID_PREFIX};
+    use nanoid::nanoid;
+
+    let mut room = ROOM_STORE
+        .get_mut(&room_id)
+        .ok_or_else(|| ServerFnError::ServerError("No such room.".to_string()))?;
+    if room.players[room.host].uuid != uuid {
+        return Err(ServerFnError::ServerError(
+            "Only the host can add bots.".to_string(),
+        ));
+    }
+    if room.state != RoomState::Waiting {
+        return Err(ServerFnError::ServerError(
+            "This room has already started.".to_string(),
+        ));
+    }
+    if room.players.len() >= MAX_PLAYERS {
+        return Err(ServerFnError::ServerError("This room is full.".to_string()));
+    }
+    let taken: Vec<String> = room
+        .players
+        .iter()
+        .map(|player| player.name.clone())
+        .collect();
+    room.players.push(RoomPlayer {
+        uuid: format!("{}{}", BOT_UUID_PREFIX, nanoid!(12)),
+        name: bot_name(&taken),
+        ready: true,
+    });
+    Ok(())
+}
+
+#[server(RemoveBot, "/api", "Cbor", "remove_bot")]
+pub async fn remove_bot(uuid: String, room_id: String, index: usize) -> Result<(), ServerFnError> {
+    use super::bot::is_bot;
+
+    let mut room = ROOM_STORE
+        .get_mut(&room_id)
+        .ok_or_else(|| ServerFnError::ServerError("No such room.".to_string()))?;
+    if room.players[room.host].uuid != uuid {
+        return Err(ServerFnError::ServerError(
+            "Only the host can remove bots.".to_string(),
+        ));
+    }
+    if room.state != RoomState::Waiting {
+        return Err(ServerFnError::ServerError(
+            "This room has already started.".to_string(),
+        ));
+    }
+    if !room
+        .players
+        .get(index)
+        .is_some_and(|player| is_bot(&player.uuid))
+    {
+        return Err(ServerFnError::ServerError("No such bot.".to_string()));
+    }
+    room.players.remove(index);
+    if index < room.host {
+        room.host -= 1;
+    }
+    Ok(())
+}
+
+// Picks the rules for the game, a preset or house rules. Has to work for any
+// table the room can seat, since players may still come and go.
+#[server(SetRoomConfig, "/api", "Cbor", "set_room_config")]
+pub async fn set_room_config(
+    uuid: String,
+    room_id: String,
+    config: GameConfig,
+) -> Result<(), ServerFnError> {
+    let mut room = ROOM_STORE
+        .get_mut(&room_id)
+        .ok_or_else(|| ServerFnError::ServerError("No such room.".to_string()))?;
+    if room.players[room.host].uuid != uuid {
+        return Err(ServerFnError::ServerError(
+            "Only the host can change the rules.".to_string(),
+        ));
+    }
+    if room.state != RoomState::Waiting {
+        return Err(ServerFnError::ServerError(
+            "This room has already started.".to_string(),
+        ));
+    }
+    (MIN_PLAYERS..=MAX_PLAYERS)
+        .try_for_each(|player_count| config.validate(player_count))
+        .map_err(ServerFnError::ServerError)?;
+    room.config = config;
+    Ok(())
+}
+
+#[server(StartRoom, "/api", "Cbor", "start_room")]
+pub async fn start_room(uuid: String, room_id: String) -> Result<(), ServerFnError> {
+    use super::{
+        database::{save_room, RoomRecord},
+        ruleset::find_ruleset,
+    };
+
+    let (ruleset, players, config, seed) = {
+        let mut room = ROOM_STORE
+            .get_mut(&room_id)
+            .ok_or_else(|| ServerFnError::ServerError("No such room.".to_string()))?;
+        if room.players[room.host].uuid != uuid {
+            return Err(ServerFnError::ServerError(
+                "Only the host can start the game.".to_string(),
+            ));
+        }
+        if room.state != RoomState::Waiting {
+            return Err(ServerFnError::ServerError(
+                "This room has already started.".to_string(),
+            ));
+        }
+        room.config
+            .validate(room.players.len())
+            .map_err(ServerFnError::ServerError)?;
+        if !room.info(&room_id, &uuid).can_start() {
+            return Err(ServerFnError::ServerError(
+                "Everyone has to be ready.".to_string(),
+            ));
+        }
+        let ruleset = find_ruleset(room.ruleset).ok_or_else(|| {
+            ServerFnError::ServerError(format!("This server cannot host {}.", room.ruleset))
+        })?;
+        room.state = RoomState::Started;
+        let record = RoomRecord {
+            name: room.name.clone(),
+            host: room.host,
+        };
+        if let Err(error) = save_room(&room_id, &record) {
+            log::error!("failed to save room {}: {}", room_id, error);
+        }
+        let players = room
+            .players
+            .iter()
+            .map(|player| (player.uuid.clone(), player.name.clone()))
+            .collect();
+        (ruleset, players, room.config.clone(), room.seed)
+    };
+    ruleset.start(players, config, seed, room_id, None);
+    Ok(())
+}
@@ -0,0 +1,302 @@
+use super::{
+    database::SavedGame,
+    game::start_game,
+    game_log::GameLog,
+    timer::{next_deadline, timeout_actions},
+};
+use crate::common::{
+    card::CardColor,
+    clock::Clock,
+    config::GameConfig,
+    error::GameError,
+    game_state::{GameState, Money, RoundBoard},
+    input::{ActionInput, LegalAction},
+    player::PlayerID,
+    rules::RoundRules,
+    server_message::GameEvent,
+};
+use anyhow::Result;
+
+// GameConfig::validate bounds every payout, so scoring a game that was set up
+// with a valid config never overflows
+const PAYOUT_RANGE: &str = "payout out of the range the config allows";
+
+// A game the server can host. Every ruleset is played on the same board, hands
+// of paintings, money and auctions, so they all share GameState and the wire
+// protocol. The ruleset decides how the board is set up, what each input does,
+// what each seat gets to see, and when the game is over and who won. The auctions
+// themselves are shared, the rounds around them go through RoundRules.
+pub(crate) trait Ruleset: RoundRules + Clone + Send + Sync + 'static {
+    // recorded with every game, so a restored game keeps its rules
+    const NAME: &'static str;
+
+    // Vec<(uuid, name)>, the same seed always deals the same hands
+    fn setup(&self, players: Vec<(String, String)>, config: GameConfig, seed: u64) -> GameState;
+
+    // applies `input` and returns what happened, in order
+    fn process_input(
+        &self,
+        state: &mut GameState,
+        player: PlayerID,
+        input: ActionInput,
+        clock: &impl Clock,
+    ) -> Result<Vec<GameEvent>, GameError>;
+
+    // every move `player` may make right now
+    fn legal_actions(&self, state: &GameState, player: PlayerID) -> Vec<LegalAction>;
+
+    // what `seat` may see of the game, None for a spectator
+    fn mask(&self, state: &GameState, seat: Option<PlayerID>) -> GameState;
+
+    // when the server should step in next, `now` being when the turn started
+    fn deadline(&self, state: &GameState, now: f64, turn_timeout: Option<f64>) -> Option<f64>;
+
+    // what the server plays once the deadline passes, with the reason if any
+    fn timeout_actions(&self, state: &GameState)
+        -> Vec<(PlayerID, ActionInput, Option<GameEvent>)>;
+
+    fn is_over(&self, state: &GameState) -> bool;
+
+    // final standing per player, higher is better
+    fn scores(&self, state: &GameState) -> Vec<Money>;
+}
+
+// Modern Art as printed: four rounds of auctions, the richest player wins.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct ModernArt;
+
+impl Ruleset for ModernArt {
+    const NAME: &'static str = "Modern Art";
+
+    fn setup(&self, players: Vec<(String, String)>, config: GameConfig, seed: u64) -> GameState {
+        GameState::new(players, config, seed)
+    }
+
+    fn process_input(
+        &self,
+        state: &mut GameState,
+        player: PlayerID,
+        input: ActionInput,
+        clock: &impl Clock,
+    ) -> Result<Vec<GameEvent>, GameError> {
+        state.process_input(self, player, input, clock)
+    }
+
+    fn legal_actions(&self, state: &GameState, player: PlayerID) -> Vec<LegalAction> {
+        state.legal_actions(player)
+    }
+
+    fn mask(&self, state: &GameState, seat: Option<PlayerID>) -> GameState {
+        match seat {
+            Some(player) => state.mask(player),
+            None => state.mask_observer(),
+        }
+    }
+
+    fn deadline(&self, state: &GameState, now: f64, turn_timeout: Option<f64>) -> Option<f64> {
+        next_deadline(state, now, turn_timeout)
+    }
+
+    fn timeout_actions(
+        &self,
+        state: &GameState,
+    ) -> Vec<(PlayerID, ActionInput, Option<GameEvent>)> {
+        timeout_actions(state)
+    }
+
+    fn is_over(&self, state: &GameState) -> bool {
+        state.ended
+    }
+
+    fn scores(&self, state: &GameState) -> Vec<Money> {
+        state.money.clone()
+    }
+}
+
+impl RoundRules for ModernArt {
+    fn round_over(&self, state: &GameState) -> bool {
+        state
+            .round_board
+            .played
+            .iter()
+            .any(|count| *count >= state.config.round_end_count)
+    }
+
+    fn is_last_round(&self, state: &GameState) -> bool {
+        state.config.is_last_round(state.current_round)
+    }
+
+    // ranks the colors played this round, then every player sells all of their
+    // paintings: ranked colors pay their value summed over all rounds so far,
+    // the rest are worth nothing
+    fn score_round(&self, state: &mut GameState) -> GameEvent {
+        let round = state.current_round;
+        let rankings = state.round_board.rankings(state.config.round_values.len());
+        for (color, value) in rankings.iter().zip(state.config.round_values.iter()) {
+            state.values[round][color.index()] = *value;
+        }
+        let ranking: Vec<(CardColor, Money)> = rankings
+            .into_iter()
+            .map(|color| {
+                let total = (0..=round)
+                    .map(|round| state.values[round][color.index()])
+                    .try_fold(0 as Money, Money::checked_add)
+                    .expect(PAYOUT_RANGE);
+                (color, total)
+            })
+            .collect();
+
+        let discard = &mut state.discard;
+        let payouts: Vec<Money> = state
+            .owned_cards
+            .iter_mut()
+            .map(|owned| {
+                discard.extend(owned.iter().copied());
+                owned
+                    .drain(..)
+                    .filter_map(|card| {
+                        ranking
+                            .iter()
+                            .find(|(color, _)| *color == card.color)
+                            .map(|(_, value)| *value)
+                    })
+                    .try_fold(0 as Money, Money::checked_add)
+                    .expect(PAYOUT_RANGE)
+            })
+            .collect();
+        for (money, earned) in state.money.iter_mut().zip(payouts.iter()) {
+            *money = money.checked_add(*earned).expect(PAYOUT_RANGE);
+            state.issued = state.issued.checked_add(*earned).expect(PAYOUT_RANGE);
+        }
+        state.round_board = RoundBoard::default();
+
+        GameEvent::RoundScored {
+            round,
+            ranking,
+            payouts,
+        }
+    }
+
+    // deals off the top of the pool, as far as it goes round the table
+    fn deal_round(&self, state: &mut GameState) -> Option<GameEvent> {
+        state.current_round += 1;
+        let count = state
+            .config
+            .cards_to_deal(state.players.len(), state.current_round)
+            .min(state.pool.len() / state.players.len());
+        if count == 0 {
+            return None;
+        }
+        for deck in state.deck.iter_mut() {
+            deck.extend(state.pool.drain(..count));
+        }
+        Some(GameEvent::CardsDealt {
+            round: state.current_round,
+            count,
+        })
+    }
+}
+
+// A ruleset picked by name at run time, from a room or a saved game. Ruleset
+// itself is generic, this is what the rest of the server gets to hold.
+pub(crate) trait HostedRuleset: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // `config` and `seed` only apply to a new game, a saved one keeps what it started with
+    fn start(
+        &self,
+        players: Vec<(String, String)>,
+        config: GameConfig,
+        seed: u64,
+        game_id: String,
+        saved: Option<SavedGame>,
+    );
+
+    fn replay(&self, log: &GameLog, step: usize) -> Result<GameState>;
+
+    fn mask(&self, state: &GameState, seat: Option<PlayerID>) -> GameState;
+}
+
+impl<R: Ruleset> HostedRuleset for R {
+    fn name(&self) -> &'static str {
+        R::NAME
+    }
+
+    fn start(
+        &self,
+        players: Vec<(String, String)>,
+        config: GameConfig,
+        seed: u64,
+        game_id: String,
+        saved: Option<SavedGame>,
+    ) {
+        start_game(self.clone(), players, config, seed, game_id, saved);
+    }
+
+    fn replay(&self, log: &GameLog, step: usize) -> Result<GameState> {
+        log.replay(self, step)
+    }
+
+    fn mask(&self, state: &GameState, seat: Option<PlayerID>) -> GameState {
+        Ruleset::mask(self, state, seat)
+    }
+}
+
+// every ruleset the server can host, new rooms play the first one
+const RULESETS: &[&dyn HostedRuleset] = &[&ModernArt];
+
+pub(crate) fn default_ruleset() -> &'static dyn HostedRuleset {
+    RULESETS[0]
+}
+
+pub(crate) fn find_ruleset(name: &str) -> Option<&'static dyn HostedRuleset> {
+    RULESETS
+        .iter()
+        .copied()
+        .find(|ruleset| ruleset.name() == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ModernArt;
+    use crate::common::{
+        clock::FixedClock, fixtures::new_game, game_state::GameStage, input::ActionInput,
+        server_message::GameEvent,
+    };
+
+    #[test]
+    fn every_round_end_deals_the_next_round() {
+        for (count, deals) in [(3, [10, 6, 6, 0]), (4, [9, 4, 4, 0]), (5, [8, 3, 3, 0])] {
+            let mut state = new_game(count, 0);
+            assert!(state.deck.iter().all(|hand| hand.len() == deals[0]));
+            for (round, deal) in deals.into_iter().enumerate().skip(1) {
+                let GameStage::WaitingForNextCard(player) = state.stage else {
+                    panic!("round {} did not start with a card", round);
+                };
+                // whatever they play next is the fifth of its color
+                let card = state.deck[player][0];
+                state.round_board.played[card.color.index()] = 4;
+                let mut sizes: Vec<usize> = state.deck.iter().map(Vec::len).collect();
+                sizes[player] -= 1;
+
+                let events = state
+                    .process_input(
+                        &ModernArt,
+                        player,
+                        ActionInput::PlayCard(card.id),
+                        &FixedClock(0.0),
+                    )
+                    .unwrap();
+                assert_eq!(state.current_round, round);
+                let dealt = events.iter().find_map(|event| match event {
+                    GameEvent::CardsDealt { round, count } => Some((*round, *count)),
+                    _ => None,
+                });
+                assert_eq!(dealt, (deal > 0).then_some((round, deal)));
+                for (hand, size) in state.deck.iter().zip(sizes) {
+                    assert_eq!(hand.len(), size + deal, "round {} for {}", round, count);
+                }
+            }
+        }
+    }
+}
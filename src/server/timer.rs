@@ -0,0 +1,195 @@
+use super::bot::is_bot;
+use crate::common::{
+    game_state::{AuctionState, GameStage, GameState},
+    input::{ActionInput, BidOptionalInner, MarkedReactionInner, PlayCardOptionalInner},
+    player::PlayerID,
+    server_message::GameEvent,
+};
+use once_cell::sync::Lazy;
+
+// seconds a player may sit on their turn before the server acts for them,
+// override with MART_TURN_TIMEOUT, 0 turns it off
+const DEFAULT_TURN_TIMEOUT: f64 = 90.0;
+
+pub static TURN_TIMEOUT: Lazy<Option<f64>> = Lazy::new(|| {
+    let timeout = std::env::var("MART_TURN_TIMEOUT")
+        .ok()
+        .and_then(|value| value.parse::<f64>().ok())
+        .unwrap_or(DEFAULT_TURN_TIMEOUT);
+    (timeout > 0.0).then_some(timeout)
+});
+
+// bots are always there, they alone don't keep the clock running
+pub(crate) fn humans_connected(state: &GameState) -> bool {
+    state
+        .players
+        .iter()
+        .any(|player| player.connected && !is_bot(&player.uuid))
+}
+
+// When the server should step in next, in seconds since UNIX epoch.
+// `now` is the moment the current turn started.
+pub(crate) fn next_deadline(state: &GameState, now: f64, turn_timeout: Option<f64>) -> Option<f64> {
+    if state.ended || !humans_connected(state) {
+        // nobody to play against, wait for someone to come back
+        return None;
+    }
+    match &state.stage {
+        GameStage::AuctionInAction {
+            state: AuctionState::Free { time_end, .. },
+            ..
+        } => Some(*time_end),
+        _ => turn_timeout.map(|timeout| now + timeout),
+    }
+}
+
+// The inputs the server plays once a deadline passes, each with the event that
+// tells the table why it happened, if the input itself does not already say so.
+pub(crate) fn timeout_actions(
+    state: &GameState,
+) -> Vec<(PlayerID, ActionInput, Option<GameEvent>)> {
+    let timeout = |player: PlayerID, action: ActionInput| {
+        (
+            player,
+            action,
+            Some(GameEvent::TurnTimeout { player, action }),
+        )
+    };
+    match &state.stage {
+        GameStage::WaitingForNextCard(player) => state.deck[*player]
+            .first()
+            .map(|card| timeout(*player, ActionInput::PlayCard(card.id)))
+            .into_iter()
+            .collect(),
+        GameStage::WaitingForDoubleTarget { current, .. } => vec![timeout(
+            *current,
+            ActionInput::PlayCardOptional(PlayCardOptionalInner::Pass),
+        )],
+        GameStage::WaitingForMarkedPrice { starter, .. } => {
+            vec![timeout(*starter, ActionInput::AssignMarkedPrice(0))]
+        }
+        GameStage::AuctionInAction { state, .. } => match state {
+            // the call announces itself
+            AuctionState::Free { host, .. } => vec![(*host, ActionInput::Call, None)],
+            AuctionState::Circle { current_player, .. } => vec![timeout(
+                *current_player,
+                ActionInput::BidOptional(BidOptionalInner::Pass),
+            )],
+            AuctionState::Marked { current, .. } => vec![timeout(
+                *current,
+                ActionInput::MarkedReaction(MarkedReactionInner::Pass),
+            )],
+            AuctionState::Fist {
+                host, action_taken, ..
+            } => action_taken
+                .iter()
+                .enumerate()
+                .filter(|(_, taken)| !**taken)
+                .map(|(player, _)| timeout(player, ActionInput::Bid(0)))
+                .chain(std::iter::once(timeout(*host, ActionInput::Call)))
+                .collect(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{next_deadline, timeout_actions};
+    use crate::{
+        common::{
+            card::{AuctionType, CardColor},
+            error::GameError,
+            fixtures::{empty_table, take},
+            game_state::GameState,
+            input::{ActionInput, BidOptionalInner},
+            server_message::GameEvent,
+        },
+        server::{
+            bot::BOT_UUID_PREFIX,
+            clock::{Clock, ManualClock},
+            ruleset::ModernArt,
+        },
+    };
+
+    const TIMEOUT: Option<f64> = Some(90.0);
+
+    // player 0 is at the table with a card of this kind, and another in every hand
+    fn table_with(ty: AuctionType) -> (GameState, ActionInput) {
+        let mut state = empty_table(3);
+        for player in 0..3 {
+            let card = take(&mut state, CardColor::Green, AuctionType::Fist);
+            state.deck[player].push(card);
+        }
+        let card = take(&mut state, CardColor::Red, ty);
+        state.deck[0].insert(0, card);
+        state.connect(0);
+        (state, ActionInput::PlayCard(card.id))
+    }
+
+    #[test]
+    fn a_free_auction_is_called_once_its_window_is_over() {
+        let (mut state, play) = table_with(AuctionType::Free);
+        let clock = ManualClock::new(1000.0);
+        state.process_input(&ModernArt, 0, play, &clock).unwrap();
+        let deadline = next_deadline(&state, clock.now(), TIMEOUT).unwrap();
+        // the window, not the turn timeout
+        assert_eq!(deadline, 1000.0 + state.config.free_auction_window);
+        let actions = timeout_actions(&state);
+        assert!(matches!(actions[..], [(0, ActionInput::Call, None)]));
+
+        clock.advance(deadline - clock.now());
+        assert!(matches!(
+            state.process_input(&ModernArt, 0, ActionInput::Call, &clock),
+            Err(GameError::CallTooEarly { .. })
+        ));
+        // the actor's timer fires a moment after the deadline
+        clock.advance(0.05);
+        let events = state
+            .process_input(&ModernArt, 0, ActionInput::Call, &clock)
+            .unwrap();
+        assert!(matches!(
+            events[..],
+            [GameEvent::AuctionCall { host: 0, calls: 1 }]
+        ));
+        assert!(next_deadline(&state, clock.now(), TIMEOUT).unwrap() > deadline);
+    }
+
+    #[test]
+    fn a_turn_timeout_plays_the_first_card_then_passes() {
+        let (mut state, play) = table_with(AuctionType::Circle);
+        let clock = ManualClock::new(1000.0);
+        assert_eq!(next_deadline(&state, clock.now(), TIMEOUT), Some(1090.0));
+        let actions = timeout_actions(&state);
+        assert!(matches!(
+            actions[..],
+            [(0, action, Some(GameEvent::TurnTimeout { player: 0, .. }))] if action == play
+        ));
+        state.process_input(&ModernArt, 0, play, &clock).unwrap();
+
+        // the circle goes round from the player after the auctioneer
+        clock.advance(90.0);
+        assert_eq!(next_deadline(&state, clock.now(), TIMEOUT), Some(1180.0));
+        let pass = ActionInput::BidOptional(BidOptionalInner::Pass);
+        let actions = timeout_actions(&state);
+        assert!(matches!(
+            actions[..],
+            [(1, action, Some(GameEvent::TurnTimeout { player: 1, .. }))] if action == pass
+        ));
+        state.process_input(&ModernArt, 1, pass, &clock).unwrap();
+        assert!(matches!(timeout_actions(&state)[..], [(2, action, _)] if action == pass));
+    }
+
+    #[test]
+    fn no_deadline_runs_while_no_human_is_connected() {
+        let (mut state, _) = table_with(AuctionType::Circle);
+        state.disconnect(0);
+        assert_eq!(next_deadline(&state, 0.0, TIMEOUT), None);
+        state.players[1].uuid = format!("{}1", BOT_UUID_PREFIX);
+        state.connect(1);
+        assert_eq!(next_deadline(&state, 0.0, TIMEOUT), None);
+        state.connect(2);
+        assert_eq!(next_deadline(&state, 0.0, TIMEOUT), Some(90.0));
+        state.disconnect(2);
+        assert_eq!(next_deadline(&state, 0.0, TIMEOUT), None);
+    }
+}
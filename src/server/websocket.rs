@@ -1,4 +1,12 @@
-use crate::common::{input::GameInput, player::PlayerID, server_message::ServerMessage};
+use super::{database::load_game, ruleset::find_ruleset};
+use crate::common::{
+    error::GameError,
+    game_state::GameState,
+    handshake::{Capabilities, Hello, HelloReply, PROTOCOL_VERSION},
+    input::{GameInput, SessionID},
+    player::PlayerID,
+    server_message::ServerMessage,
+};
 use axum::{
     body::Body,
     extract::{
@@ -9,35 +17,100 @@ use axum::{
     response::{IntoResponse, Response},
 };
 use dashmap::DashMap;
-use futures::{SinkExt, StreamExt};
+use futures::{
+    stream::{SplitSink, SplitStream},
+    SinkExt, StreamExt,
+};
 use once_cell::sync::Lazy;
 use rkyv::{from_bytes, to_bytes};
-use tokio::sync::{broadcast, mpsc};
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+use tokio::{
+    sync::{broadcast, mpsc},
+    time::{sleep_until, Instant},
+};
+
+// how long a fresh socket may take to say hello
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// seconds spectators lag behind the table, so they cannot whisper to a player,
+// override with MART_SPECTATOR_DELAY
+const DEFAULT_SPECTATOR_DELAY: f64 = 0.0;
+
+pub static SPECTATOR_DELAY: Lazy<Duration> = Lazy::new(|| {
+    let delay = std::env::var("MART_SPECTATOR_DELAY")
+        .ok()
+        .and_then(|value| value.parse::<f64>().ok())
+        .unwrap_or(DEFAULT_SPECTATOR_DELAY);
+    Duration::from_secs_f64(delay.max(0.0))
+});
 
-pub struct GameInfo {
+static NEXT_SESSION: AtomicU64 = AtomicU64::new(1);
+
+pub(crate) fn new_session() -> SessionID {
+    NEXT_SESSION.fetch_add(1, Ordering::Relaxed)
+}
+
+// who a broadcast message is meant for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Audience {
+    Everyone,
+    Player(PlayerID),
+    // a single socket, whoever sits there now
+    Session(SessionID),
+    Spectators,
+}
+
+impl Audience {
+    // `seat` is None on a spectator's socket
+    pub(crate) fn includes(self, seat: Option<PlayerID>, session: SessionID) -> bool {
+        match self {
+            Audience::Everyone => true,
+            Audience::Player(id) => seat == Some(id),
+            Audience::Session(id) => session == id,
+            Audience::Spectators => seat.is_none(),
+        }
+    }
+}
+
+// spectators have no seat, so they get their own channel into the game
+#[derive(Debug, Clone, Copy)]
+pub enum SpectatorInput {
+    Join,
+    Leave,
+    // answered on the asking socket alone
+    RequestState { session: SessionID },
+}
+
+// What the rest of the server knows of a running game. The actor alone holds
+// the ruleset, every ruleset speaks the same protocol over these channels, so
+// sockets never need to know which one they are talking to.
+pub(crate) struct GameInfo {
+    // the name of the ruleset the actor plays by
+    pub ruleset: &'static str,
     pub players: Vec<(String, String)>, // (uuid, name)
-    pub read: broadcast::Sender<(Option<PlayerID>, ServerMessage)>,
+    pub read: broadcast::Sender<(Audience, ServerMessage)>,
     pub write: mpsc::UnboundedSender<(PlayerID, GameInput)>,
+    pub spectate: mpsc::UnboundedSender<SpectatorInput>,
 }
 
-pub static GAME_INFO_STORE: Lazy<DashMap<String, GameInfo>> = Lazy::new(|| DashMap::new());
+pub(crate) static GAME_INFO_STORE: Lazy<DashMap<String, GameInfo>> = Lazy::new(|| DashMap::new());
 
+// anyone who is not seated at the table may still watch
 pub async fn game_websocket(
-    Path(game_id): Path<String>,
-    Path(uuid): Path<String>,
+    Path((game_id, uuid)): Path<(String, String)>,
     ws: WebSocketUpgrade,
 ) -> impl IntoResponse {
     if let Some(inner) = GAME_INFO_STORE.get(&game_id) {
-        if let Some(player_id) = inner.players.iter().position(|player| player.0 == uuid) {
-            ws.on_upgrade(move |ws| handle_game_websocket(ws, game_id, player_id))
-                .into_response()
-        } else {
-            Response::builder()
-                .status(StatusCode::FORBIDDEN)
-                .body(Body::from("You are not allowed to join this game."))
-                .unwrap()
-                .into_response()
-        }
+        let seat = inner.players.iter().position(|player| player.0 == uuid);
+        ws.on_upgrade(move |ws| handle_game_websocket(ws, game_id, seat))
+            .into_response()
+    } else if let Some(state) = final_state(&game_id, &uuid) {
+        ws.on_upgrade(move |ws| handle_ended_websocket(ws, state))
+            .into_response()
     } else {
         Response::builder()
             .status(StatusCode::NOT_FOUND)
@@ -47,30 +120,150 @@ pub async fn game_websocket(
     }
 }
 
-async fn handle_game_websocket(socket: WebSocket, game_id: String, player_id: PlayerID) {
+// an ended game has no actor left, it is rebuilt from its log as the viewer sees it
+fn final_state(game_id: &str, uuid: &str) -> Option<GameState> {
+    let (log, ended) = match load_game(game_id) {
+        Ok(game) => game?,
+        Err(error) => {
+            log::error!("failed to load game {}: {}", game_id, error);
+            return None;
+        }
+    };
+    if !ended {
+        return None;
+    }
+    let ruleset = find_ruleset(&log.ruleset)?;
+    let state = match ruleset.replay(&log, log.entries.len()) {
+        Ok(state) => state,
+        Err(error) => {
+            log::warn!("could not replay game {}: {}", game_id, error);
+            return None;
+        }
+    };
+    let seat = log.players.iter().position(|player| player.0 == uuid);
+    Some(ruleset.mask(&state, seat))
+}
+
+// answers a socket to an ended game with how it ended, then hangs up
+async fn handle_ended_websocket(socket: WebSocket, state: GameState) {
     let (mut sender, mut receiver) = socket.split();
+    if handshake(&mut sender, &mut receiver).await.is_some() {
+        let data = to_bytes::<_, 4>(&ServerMessage::StateUpdate(state))
+            .unwrap()
+            .to_vec();
+        let _ = sender.send(Message::Binary(data)).await;
+    }
+    let _ = sender.close().await;
+}
 
-    let game_info = GAME_INFO_STORE.get(&game_id).unwrap();
-    let mut read = game_info.read.subscribe();
-    let write = game_info.write.clone();
+async fn handle_game_websocket(socket: WebSocket, game_id: String, seat: Option<PlayerID>) {
+    let (mut sender, mut receiver) = socket.split();
 
-    write.send((player_id, GameInput::Connect)).unwrap();
+    let Some(capabilities) = handshake(&mut sender, &mut receiver).await else {
+        let _ = sender.close().await;
+        return;
+    };
 
+    // don't hold on to the store entry, stopping the game needs to remove it
+    let Some((mut read, write, spectate)) = GAME_INFO_STORE.get(&game_id).map(|game_info| {
+        log::debug!("socket joined {} game {}", game_info.ruleset, game_id);
+        (
+            game_info.read.subscribe(),
+            game_info.write.clone(),
+            game_info.spectate.clone(),
+        )
+    }) else {
+        let _ = sender.close().await;
+        return;
+    };
+    // answers meant for this socket alone
+    let (reply, mut replies) = mpsc::unbounded_channel();
+    let session = new_session();
+
+    match seat {
+        Some(player_id) => {
+            let _ = write.send((
+                player_id,
+                GameInput::Connect {
+                    capabilities,
+                    session,
+                },
+            ));
+        }
+        None => {
+            let _ = spectate.send(SpectatorInput::Join);
+        }
+    }
+
+    let write_clone = write.clone();
+    let spectate_clone = spectate.clone();
     let mut write_task = tokio::spawn(async move {
-        while let Some(Ok(Message::Binary(data))) = receiver.next().await {
-            if let Ok(input) = from_bytes::<GameInput>(&data) {
-                write.send((player_id, input)).unwrap();
-            }
+        while let Some(Ok(message)) = receiver.next().await {
+            let decoded = match message {
+                Message::Binary(data) => {
+                    from_bytes::<GameInput>(&data).map_err(|_| "Could not decode the frame.")
+                }
+                Message::Text(_) => Err("Only binary frames are understood."),
+                Message::Close(_) => break,
+                Message::Ping(_) | Message::Pong(_) => continue,
+            };
+            let response = match (decoded, seat) {
+                (Ok(GameInput::Connect { .. } | GameInput::Disconnect { .. }), _) => {
+                    ServerMessage::ProtocolError(
+                        "Connections are reported by the server.".to_string(),
+                    )
+                }
+                (Ok(input), Some(player_id)) => {
+                    let _ = write_clone.send((player_id, input));
+                    continue;
+                }
+                (Ok(GameInput::RequestState), None) => {
+                    let _ = spectate_clone.send(SpectatorInput::RequestState { session });
+                    continue;
+                }
+                (Ok(GameInput::Action { seq, input }), None) => ServerMessage::Rejected {
+                    seq,
+                    error: GameError::Spectating,
+                    input,
+                },
+                (Err(error), _) => ServerMessage::ProtocolError(error.to_string()),
+            };
+            let _ = reply.send(response);
         }
     });
 
+    let delay = match seat {
+        Some(_) => Duration::ZERO,
+        None => *SPECTATOR_DELAY,
+    };
     let mut read_task = tokio::spawn(async move {
-        while let Ok((target, message)) = read.recv().await {
-            if target.is_none() | target.is_some_and(|id| id == player_id) {
-                let data = to_bytes::<_, 4>(&message).unwrap().to_vec();
-                if sender.send(Message::Binary(data)).await.is_err() {
-                    break;
+        // messages waiting out the spectator delay, in order of arrival
+        let mut delayed: VecDeque<(Instant, ServerMessage)> = VecDeque::new();
+        loop {
+            let due = delayed.front().map(|(due, _)| *due);
+            let message = tokio::select! {
+                received = read.recv() => {
+                    let Ok((audience, message)) = received else {
+                        break;
+                    };
+                    if !audience.includes(seat, session) {
+                        continue;
+                    }
+                    if !delay.is_zero() {
+                        delayed.push_back((Instant::now() + delay, message));
+                        continue;
+                    }
+                    message
+                }
+                Some(message) = replies.recv() => message,
+                _ = sleep_until(due.unwrap_or_else(Instant::now)), if due.is_some() => {
+                    delayed.pop_front().unwrap().1
                 }
+            };
+            let replaced = matches!(message, ServerMessage::Disconnect);
+            let data = to_bytes::<_, 4>(&message).unwrap().to_vec();
+            if sender.send(Message::Binary(data)).await.is_err() || replaced {
+                break;
             }
         }
     });
@@ -80,9 +273,55 @@ async fn handle_game_websocket(socket: WebSocket, game_id: String, player_id: Pl
         _ = (&mut read_task) => write_task.abort()
     };
 
-    game_info
-        .write
-        .send((player_id, GameInput::Disconnect))
-        .unwrap();
+    match seat {
+        Some(player_id) => {
+            let _ = write.send((player_id, GameInput::Disconnect { session }));
+        }
+        None => {
+            let _ = spectate.send(SpectatorInput::Leave);
+        }
+    }
 }
 
+// Waits for the client's Hello and answers it. Returns the client's capabilities
+// if it speaks our protocol, otherwise it has been told to reload.
+async fn handshake(
+    sender: &mut SplitSink<WebSocket, Message>,
+    receiver: &mut SplitStream<WebSocket>,
+) -> Option<Capabilities> {
+    let hello = match tokio::time::timeout(HANDSHAKE_TIMEOUT, receiver.next()).await {
+        Ok(Some(Ok(Message::Binary(data)))) => from_bytes::<Hello>(&data).ok(),
+        Ok(Some(Ok(_))) => None,
+        // gone or silent, nobody to answer
+        _ => return None,
+    };
+    let reject = |message: String| HelloReply {
+        version: PROTOCOL_VERSION,
+        accepted: false,
+        message,
+    };
+    let (reply, capabilities) = match hello {
+        Some(hello) if hello.version == PROTOCOL_VERSION => (
+            HelloReply {
+                version: PROTOCOL_VERSION,
+                accepted: true,
+                message: String::new(),
+            },
+            Some(hello.capabilities),
+        ),
+        Some(hello) => (
+            reject(format!(
+                "This page speaks protocol {} but the server speaks {}, please reload.",
+                hello.version, PROTOCOL_VERSION
+            )),
+            None,
+        ),
+        None => (
+            reject("Unrecognized handshake, please reload.".to_string()),
+            None,
+        ),
+    };
+    let data = to_bytes::<_, 64>(&reply).unwrap().to_vec();
+    sender.send(Message::Binary(data)).await.ok()?;
+    capabilities
+}